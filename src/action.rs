@@ -0,0 +1,63 @@
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    Quit,
+    Up,
+    Down,
+    Left,
+    Right,
+    First,
+    Last,
+    NextPanel,
+    Confirm,
+    Cancel,
+    NewProject,
+    EditProject,
+    DeleteProject,
+    StartTimer,
+    StopTimer,
+    ExportCsv,
+    CommandPalette,
+    Help,
+}
+
+impl Action {
+    // Actions offered by the command palette; moving around and confirming depend on the
+    // focused panel, so they are left out.
+    pub const COMMANDS: [Action; 9] = [
+        Action::StartTimer,
+        Action::StopTimer,
+        Action::NewProject,
+        Action::EditProject,
+        Action::DeleteProject,
+        Action::ExportCsv,
+        Action::NextPanel,
+        Action::Help,
+        Action::Quit,
+    ];
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Action::Quit => "Quit",
+            Action::Up => "Select previous item",
+            Action::Down => "Select next item",
+            Action::Left => "Select previous button",
+            Action::Right => "Select next button",
+            Action::First => "Select first item",
+            Action::Last => "Select last item",
+            Action::NextPanel => "Focus next panel",
+            Action::Confirm => "Confirm",
+            Action::Cancel => "Cancel",
+            Action::NewProject => "New project",
+            Action::EditProject => "Edit selected project",
+            Action::DeleteProject => "Delete selected project",
+            Action::StartTimer => "Start timer on selected project",
+            Action::StopTimer => "Stop running timer",
+            Action::ExportCsv => "Export timers to CSV next to data file",
+            Action::CommandPalette => "Open command palette",
+            Action::Help => "Toggle help",
+        }
+    }
+}
@@ -1,5 +1,14 @@
-use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use crossterm::event::{
+    Event, KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEvent, MouseEventKind,
+};
 use ratatui::{
+    layout::{Position, Rect},
     text::{Line, Text},
     widgets::ListState,
 };
@@ -7,8 +16,18 @@ use tui_confirm_dialog::{ButtonLabel, ConfirmDialogState, Listener};
 use tui_input::{backend::crossterm::EventHandler, Input};
 
 use crate::{
-    model::project::Project,
+    action::Action,
+    clock::Clock,
+    config::Config,
+    datetime::{Formats, Zone},
+    error::TimerError,
+    export::{self, ExportFilter, ExportOptions},
+    keymap::{KeyPress, Keymap},
+    model::{project::Project, timer::Timer},
+    palette::CommandPalette,
+    report::Report,
     repository::Repository,
+    theme::Theme,
 };
 
 pub struct StatefulList<T> {
@@ -25,6 +44,9 @@ impl<T> StatefulList<T> {
     }
 
     pub fn next(&mut self) {
+        if self.items.is_empty() {
+            return;
+        }
         let i = match self.state.selected() {
             Some(i) => {
                 if i >= self.items.len() - 1 {
@@ -39,6 +61,9 @@ impl<T> StatefulList<T> {
     }
 
     pub fn previous(&mut self) {
+        if self.items.is_empty() {
+            return;
+        }
         let i = match self.state.selected() {
             Some(i) => {
                 if i == 0 {
@@ -52,6 +77,18 @@ impl<T> StatefulList<T> {
         self.state.select(Some(i));
     }
 
+    pub fn first(&mut self) {
+        if !self.items.is_empty() {
+            self.state.select(Some(0));
+        }
+    }
+
+    pub fn last(&mut self) {
+        if !self.items.is_empty() {
+            self.state.select(Some(self.items.len() - 1));
+        }
+    }
+
     pub fn selected(&mut self) -> Option<&T> {
         match self.state.selected() {
             Some(x) => Some(&self.items[x]),
@@ -75,6 +112,32 @@ pub const PROJECT_LIST_PANEL_INDEX: usize = 1;
 pub const TIMER_LIST_PANEL_INDEX: usize = 2;
 pub const PROJECT_INPUT_PANEL_INDEX: usize = 1001;
 
+// Lines taken by one timer in the timer list.
+pub const TIMER_ITEM_HEIGHT: u16 = 3;
+
+// Where panels were drawn last time, so mouse positions can be mapped to them.
+#[derive(Debug, Clone, Default)]
+pub struct PanelAreas {
+    pub buttons: Vec<Rect>,
+    pub project_list: Rect,
+    pub project_input: Rect,
+    pub timer_list: Rect,
+}
+
+// Work done outside the app state: changes to the data file and exports. `App::update`
+// only returns them, `App::perform` carries them out.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Effect {
+    AddProject(String),
+    RenameProject(u64, String),
+    DeleteProject(u64),
+    StartTimer(u64),
+    StopTimer,
+    ExportCsv(Option<u64>),
+    // Another program changed the data file.
+    Reload,
+}
+
 #[derive(PartialEq)]
 pub enum InputMode {
     Normal,
@@ -114,21 +177,33 @@ pub struct ConfirmDialogComponent {
 pub struct App {
     pub should_quit: bool,
     pub projects: StatefulList<Project>,
+    pub timers: ListState,
     pub timer_buttons: StatefulList<ButtonState<'static>>,
     pub selected_panel_index: usize,
     pub project_input: InputComponent,
     pub error: Option<String>,
+    pub message: Option<String>,
+    pub zone: Zone,
+    pub formats: Formats,
+    pub keymap: Keymap,
+    pub theme: Theme,
+    pub show_help: bool,
+    pub big_timer: bool,
+    pub palette: Option<CommandPalette>,
+    pub areas: PanelAreas,
+    pending_keys: Vec<KeyPress>,
     pub confirm_dialog_component: ConfirmDialogComponent,
+    pub clock: Rc<dyn Clock>,
     repository: Repository,
 }
 
 impl App {
-    pub fn new(file_name: &str) -> Self {
-        let repository = Repository::new(file_name);
+    pub fn new(config: &Config, repository: Repository) -> Self {
         let (tx, rx) = std::sync::mpsc::channel();
         Self {
             should_quit: false,
             projects: StatefulList::with_items(repository.find_all().to_vec()),
+            timers: ListState::default(),
             timer_buttons: StatefulList::with_items(vec![
                 ButtonState::new("New project"),
                 ButtonState::new("Start"),
@@ -137,6 +212,16 @@ impl App {
             selected_panel_index: 0,
             project_input: InputComponent::default(),
             error: None,
+            message: None,
+            zone: config.timezone.clone(),
+            formats: config.formats(),
+            keymap: config.keymap(),
+            theme: config.theme(),
+            show_help: false,
+            big_timer: config.big_timer,
+            palette: None,
+            areas: PanelAreas::default(),
+            pending_keys: Vec::new(),
             confirm_dialog_component: ConfirmDialogComponent {
                 visible: false,
                 confirm_popup: ConfirmDialogState::default(),
@@ -144,6 +229,7 @@ impl App {
                 popup_rx: rx,
                 close_status: None,
             },
+            clock: repository.clock(),
             repository,
         }
     }
@@ -163,21 +249,153 @@ impl App {
     pub fn on_up(&mut self) {
         if self.selected_panel_index == PROJECT_LIST_PANEL_INDEX {
             self.projects.previous();
-            if let Some(selected_project) = self.projects.selected() {
-                self.project_input =
-                    InputComponent::new(selected_project.name.clone(), InputMode::Normal);
-            }
+            self.on_project_selected();
+        } else if self.selected_panel_index == TIMER_LIST_PANEL_INDEX {
+            self.select_timer(|selected, _| selected.map_or(0, |i| i.saturating_sub(1)));
         }
     }
 
     pub fn on_down(&mut self) {
         if self.selected_panel_index == PROJECT_LIST_PANEL_INDEX {
             self.projects.next();
-            if let Some(selected_project) = self.projects.selected() {
-                self.project_input =
-                    InputComponent::new(selected_project.name.clone(), InputMode::Normal);
+            self.on_project_selected();
+        } else if self.selected_panel_index == TIMER_LIST_PANEL_INDEX {
+            self.select_timer(|selected, len| selected.map_or(0, |i| (i + 1).min(len - 1)));
+        }
+    }
+
+    pub fn on_first(&mut self) {
+        if self.selected_panel_index == PROJECT_LIST_PANEL_INDEX {
+            self.projects.first();
+            self.on_project_selected();
+        } else if self.selected_panel_index == TIMER_LIST_PANEL_INDEX {
+            self.select_timer(|_, _| 0);
+        }
+    }
+
+    pub fn on_last(&mut self) {
+        if self.selected_panel_index == PROJECT_LIST_PANEL_INDEX {
+            self.projects.last();
+            self.on_project_selected();
+        } else if self.selected_panel_index == TIMER_LIST_PANEL_INDEX {
+            self.select_timer(|_, len| len - 1);
+        }
+    }
+
+    fn on_project_selected(&mut self) {
+        self.timers = ListState::default();
+        if let Some(selected_project) = self.projects.selected() {
+            self.project_input =
+                InputComponent::new(selected_project.name.clone(), InputMode::Normal);
+        }
+    }
+
+    // `index` gets the current selection and the number of timers, which is never zero.
+    fn select_timer(&mut self, index: impl Fn(Option<usize>, usize) -> usize) {
+        let len = self.timer_count();
+        if len > 0 {
+            self.timers.select(Some(index(self.timers.selected(), len)));
+        }
+    }
+
+    fn timer_count(&mut self) -> usize {
+        self.projects
+            .selected()
+            .map_or(0, |project| project.timers.len())
+    }
+
+    pub fn on_mouse(&mut self, mouse_event: MouseEvent) {
+        if let Some(effect) = self.handle_mouse(mouse_event) {
+            self.perform(effect);
+        }
+    }
+
+    fn handle_mouse(&mut self, mouse_event: MouseEvent) -> Option<Effect> {
+        if self.confirm_dialog_component.confirm_popup.is_opened() {
+            return None;
+        }
+
+        let position = Position::new(mouse_event.column, mouse_event.row);
+        match mouse_event.kind {
+            MouseEventKind::Down(MouseButton::Left) => return self.on_click(position),
+            MouseEventKind::ScrollUp | MouseEventKind::ScrollDown => {
+                let down = mouse_event.kind == MouseEventKind::ScrollDown;
+                if let Some(palette) = self.palette.as_mut() {
+                    if down {
+                        palette.next();
+                    } else {
+                        palette.previous();
+                    }
+                } else if self.areas.project_list.contains(position) {
+                    if down {
+                        self.projects.next();
+                    } else {
+                        self.projects.previous();
+                    }
+                    self.on_project_selected();
+                } else if self.areas.timer_list.contains(position) {
+                    self.select_timer(|selected, len| match (selected, down) {
+                        (None, _) => 0,
+                        (Some(i), true) => (i + 1).min(len - 1),
+                        (Some(i), false) => i.saturating_sub(1),
+                    });
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn on_click(&mut self, position: Position) -> Option<Effect> {
+        if self.show_help || self.palette.is_some() {
+            self.show_help = false;
+            self.palette = None;
+            return None;
+        }
+        if self.project_input.mode == InputMode::Editing
+            && !self.areas.project_input.contains(position)
+        {
+            self.on_cancel();
+        }
+
+        if let Some(index) = self
+            .areas
+            .buttons
+            .iter()
+            .position(|area| area.contains(position))
+        {
+            self.selected_panel_index = TIMER_BUTTONS_PANEL_INDEX;
+            self.timer_buttons.state.select(Some(index));
+            return self.on_confirm();
+        } else if self.areas.project_list.contains(position) {
+            self.selected_panel_index = PROJECT_LIST_PANEL_INDEX;
+            let offset = self.projects.state.offset();
+            if let Some(index) = list_index(self.areas.project_list, offset, 1, position) {
+                if index < self.projects.items.len() {
+                    self.projects.state.select(Some(index));
+                    self.on_project_selected();
+                }
+            }
+        } else if self.areas.timer_list.contains(position) {
+            self.selected_panel_index = TIMER_LIST_PANEL_INDEX;
+            let offset = self.timers.offset();
+            if let Some(index) =
+                list_index(self.areas.timer_list, offset, TIMER_ITEM_HEIGHT, position)
+            {
+                if index < self.timer_count() {
+                    self.timers.select(Some(index));
+                }
+            }
+        } else if self.areas.project_input.contains(position)
+            && self.project_input.mode != InputMode::Editing
+        {
+            if self.projects.selected().is_none() {
+                self.project_input = InputComponent::default();
             }
+            self.selected_panel_index = PROJECT_INPUT_PANEL_INDEX;
+            self.project_input.mode = InputMode::Editing;
         }
+        None
     }
 
     pub fn on_tab(&mut self) {
@@ -189,148 +407,698 @@ impl App {
     }
 
     pub fn on_key(&mut self, key_event: KeyEvent) {
-        if key_event.kind == KeyEventKind::Press {
-            if key_event.modifiers == KeyModifiers::CONTROL && key_event.code == KeyCode::Char('q')
-            {
-                self.should_quit = true;
-            } else {
-                match key_event.code {
-                    KeyCode::Up => self.on_up(),
-                    KeyCode::Down => self.on_down(),
-                    KeyCode::Left => self.on_left(),
-                    KeyCode::Right => self.on_right(),
-                    KeyCode::Tab => {
-                        if self.project_input.mode != InputMode::Editing {
-                            self.on_tab();
-                        }
+        if let Some(effect) = self.handle_key(key_event) {
+            self.perform(effect);
+        }
+    }
+
+    // Runs an action together with its effect.
+    pub fn dispatch(&mut self, action: Action) {
+        if let Some(effect) = self.update(action) {
+            self.perform(effect);
+        }
+    }
+
+    fn handle_key(&mut self, key_event: KeyEvent) -> Option<Effect> {
+        if key_event.kind != KeyEventKind::Press {
+            return None;
+        }
+
+        if self.confirm_dialog_component.confirm_popup.is_opened() {
+            return self.on_confirm_dialog_key(key_event);
+        }
+        if self.palette.is_some() {
+            return self.on_palette_key(key_event);
+        }
+
+        // While typing characters go to the input, other single keys are looked up for quit,
+        // confirm, cancel and help.
+        let key = KeyPress::from(key_event);
+        if self.project_input.mode == InputMode::Editing {
+            let typed = matches!(key.code, KeyCode::Char(_)) && key.modifiers.is_empty();
+            return match self.keymap.action(key).filter(|_| !typed) {
+                Some(
+                    action @ (Action::Quit | Action::Confirm | Action::Cancel | Action::Help),
+                ) => self.update(action),
+                _ => {
+                    self.project_input
+                        .input
+                        .handle_event(&Event::Key(key_event));
+                    None
+                }
+            };
+        }
+
+        self.keymap
+            .resolve(&mut self.pending_keys, key)
+            .and_then(|action| self.update(action))
+    }
+
+    // Like editing, characters go to the palette input and other keys are looked up.
+    fn on_palette_key(&mut self, key_event: KeyEvent) -> Option<Effect> {
+        let palette = self.palette.as_mut()?;
+        let key = KeyPress::from(key_event);
+        let typed = matches!(key.code, KeyCode::Char(_)) && key.modifiers.is_empty();
+        match self.keymap.action(key).filter(|_| !typed) {
+            Some(Action::Quit) => self.should_quit = true,
+            Some(Action::Cancel | Action::CommandPalette) => self.palette = None,
+            Some(Action::Up) => palette.previous(),
+            Some(Action::Down) => palette.next(),
+            Some(Action::Confirm) => {
+                let entry = palette.selected().map(|entry| (entry.action, entry.project));
+                self.palette = None;
+                if let Some((action, project)) = entry {
+                    return self.run_command(action, project);
+                }
+            }
+            _ => {
+                if palette
+                    .input
+                    .handle_event(&Event::Key(key_event))
+                    .is_some_and(|changed| changed.value)
+                {
+                    palette.update_matches();
+                }
+            }
+        }
+        None
+    }
+
+    // Palette commands don't depend on the focused panel, so the project list is focused
+    // for the ones working on the selected project.
+    fn run_command(&mut self, action: Action, project_id: Option<u64>) -> Option<Effect> {
+        if let Some(index) = project_id
+            .and_then(|id| self.projects.items.iter().position(|x| x.id == id))
+        {
+            self.projects.state.select(Some(index));
+            self.on_project_selected();
+        }
+        if matches!(
+            action,
+            Action::EditProject | Action::DeleteProject | Action::ExportCsv
+        ) {
+            self.selected_panel_index = PROJECT_LIST_PANEL_INDEX;
+        }
+        self.update(action)
+    }
+
+    // Applies the action to the app state. Nothing is written here; changes to the data
+    // file and exports are returned as an effect.
+    pub fn update(&mut self, action: Action) -> Option<Effect> {
+        if self.show_help {
+            match action {
+                Action::Quit => self.should_quit = true,
+                Action::Help | Action::Cancel | Action::Confirm => self.show_help = false,
+                _ => {}
+            }
+            return None;
+        }
+
+        match action {
+            Action::Help => self.show_help = true,
+            Action::Quit => self.should_quit = true,
+            Action::Up => self.on_up(),
+            Action::Down => self.on_down(),
+            Action::Left => self.on_left(),
+            Action::Right => self.on_right(),
+            Action::First => self.on_first(),
+            Action::Last => self.on_last(),
+            Action::NextPanel => self.on_tab(),
+            Action::Confirm => return self.on_confirm(),
+            Action::Cancel => self.on_cancel(),
+            Action::NewProject => self.on_new_project(),
+            Action::StartTimer => return self.start_timer(),
+            Action::StopTimer => return Some(Effect::StopTimer),
+            Action::CommandPalette => {
+                self.palette = Some(CommandPalette::new(&self.keymap, &self.projects.items));
+            }
+            Action::EditProject => {
+                if self.selected_panel_index == PROJECT_LIST_PANEL_INDEX
+                    && self.projects.selected().is_some()
+                {
+                    self.selected_panel_index = PROJECT_INPUT_PANEL_INDEX;
+                    self.project_input.mode = InputMode::Editing;
+                }
+            }
+            Action::DeleteProject => {
+                if self.selected_panel_index == PROJECT_LIST_PANEL_INDEX {
+                    self.open_delete_dialog();
+                }
+            }
+            Action::ExportCsv => {
+                if self.selected_panel_index == PROJECT_LIST_PANEL_INDEX {
+                    let project_id = self.projects.selected().map(|project| project.id);
+                    return Some(Effect::ExportCsv(project_id));
+                }
+            }
+        }
+        None
+    }
+
+    fn on_confirm(&mut self) -> Option<Effect> {
+        if self.selected_panel_index == PROJECT_INPUT_PANEL_INDEX {
+            let new_project_name = self.project_input.input.value().to_string();
+            let effect = match self.projects.selected() {
+                Some(project) => Effect::RenameProject(project.id, new_project_name.clone()),
+                None => Effect::AddProject(new_project_name.clone()),
+            };
+            self.project_input = InputComponent::new(new_project_name, InputMode::Normal);
+            self.selected_panel_index = PROJECT_LIST_PANEL_INDEX;
+            return Some(effect);
+        } else if self.selected_panel_index == TIMER_BUTTONS_PANEL_INDEX {
+            match self.timer_buttons.selected().map(|button| button.text) {
+                Some("New project") => self.on_new_project(),
+                Some("Start") => return self.start_timer(),
+                Some("Stop") => return Some(Effect::StopTimer),
+                _ => {}
+            }
+        }
+        None
+    }
+
+    fn on_new_project(&mut self) {
+        self.projects.state.select(None);
+        self.timers = ListState::default();
+        self.selected_panel_index = PROJECT_INPUT_PANEL_INDEX;
+        self.project_input = InputComponent::new(String::new(), InputMode::Editing);
+    }
+
+    fn on_cancel(&mut self) {
+        if self.selected_panel_index == PROJECT_INPUT_PANEL_INDEX {
+            self.project_input.mode = InputMode::Normal;
+            if self.projects.selected().is_some() {
+                let project_name = self.projects.selected().unwrap().name.clone();
+                self.project_input = InputComponent::new(project_name, InputMode::Normal);
+                self.selected_panel_index = PROJECT_LIST_PANEL_INDEX;
+            }
+        }
+    }
+
+    fn open_delete_dialog(&mut self) {
+        let Some(project) = self.projects.selected() else {
+            return;
+        };
+        let project_name = project.name.clone();
+
+        let x = ConfirmDialogState::default()
+            .modal(false)
+            .with_title("Delete project")
+            .with_text(Text::from(vec![
+                Line::from(format!(
+                    "Are you sure you want to delete project with name: {}?",
+                    project_name
+                )),
+                Line::from(""),
+            ]))
+            .with_yes_button(ButtonLabel::from("Yes").unwrap())
+            .with_no_button(ButtonLabel::from("No").unwrap())
+            .with_yes_button_selected(false)
+            .with_listener(Some(self.confirm_dialog_component.popup_tx.clone()));
+
+        self.confirm_dialog_component.confirm_popup = x.open();
+    }
+
+    fn on_confirm_dialog_key(&mut self, key_event: KeyEvent) -> Option<Effect> {
+        self.confirm_dialog_component
+            .confirm_popup
+            .handle(KeyEvent::new(key_event.code, key_event.modifiers));
+        if let Ok((_, Some(true))) = self.confirm_dialog_component.popup_rx.try_recv() {
+            return self
+                .projects
+                .selected()
+                .map(|project| Effect::DeleteProject(project.id));
+        }
+        None
+    }
+
+    fn start_timer(&mut self) -> Option<Effect> {
+        match self.projects.selected() {
+            Some(project) => Some(Effect::StartTimer(project.id)),
+            None => {
+                self.error = Some("No project selected.".to_string());
+                None
+            }
+        }
+    }
+
+    // The only place where the data file is changed or exports are written.
+    pub fn perform(&mut self, effect: Effect) {
+        match effect {
+            Effect::AddProject(name) => {
+                if let Err(err) = self.repository.add_project(name) {
+                    self.error = Some(err.details);
+                }
+                self.reload_projects();
+            }
+            Effect::RenameProject(project_id, name) => {
+                let result = self.repository.update_project(project_id, |project| {
+                    project.name = name;
+                    Ok(())
+                });
+                if let Err(err) = result {
+                    self.error = Some(err.details);
+                }
+                self.reload_projects();
+            }
+            Effect::DeleteProject(project_id) => {
+                if let Err(err) = self.repository.delete_project(project_id) {
+                    self.error = Some(err.details);
+                }
+                self.reload_projects();
+            }
+            Effect::StartTimer(project_id) => {
+                match self.repository.start_timer(project_id) {
+                    Ok(_) => {
+                        let name = self
+                            .repository
+                            .find_all()
+                            .iter()
+                            .find(|project| project.id == project_id)
+                            .map(|project| project.name.clone())
+                            .unwrap_or_default();
+                        self.error = None;
+                        self.message = Some(format!("Started timer on {}", name));
                     }
-                    c => {
-                        if self.confirm_dialog_component.confirm_popup.is_opened()
-                            && self
-                                .confirm_dialog_component
-                                .confirm_popup
-                                .handle(KeyEvent::new(key_event.code, key_event.modifiers))
-                            && c == KeyCode::Char('y')
-                        {
-                            let project_id = self.projects.selected().unwrap().id;
-                            self.delete_project(project_id);
-                            self.projects =
-                                StatefulList::with_items(self.repository.find_all().to_vec())
-                        } else if self.selected_panel_index == PROJECT_LIST_PANEL_INDEX {
-                            match c {
-                                KeyCode::Char('e') => {
-                                    if self.projects.selected().is_some() {
-                                        self.selected_panel_index = PROJECT_INPUT_PANEL_INDEX;
-                                        self.project_input.mode = InputMode::Editing;
-                                    }
-                                }
-                                KeyCode::Char('d') => {
-                                    if self.projects.selected().is_some() {
-                                        let project_name =
-                                            self.projects.selected().unwrap().name.clone();
-
-                                        let x = ConfirmDialogState::default()
-                                            .modal(false)
-                                            .with_title("Delete project")
-                                            .with_text(Text::from(vec![
-                                                Line::from(format!(
-                                                    "Are you sure you want to delete project with name: {}?",
-                                                    project_name
-                                                )),
-                                                Line::from(""),
-                                            ]))
-                                            .with_yes_button(ButtonLabel::from("Yes").unwrap())
-                                            .with_no_button(ButtonLabel::from("No").unwrap())
-                                            .with_yes_button_selected(false)
-                                            .with_listener(Some(
-                                                self.confirm_dialog_component.popup_tx.clone(),
-                                            ));
-
-                                        self.confirm_dialog_component.confirm_popup = x.open();
-                                    }
-                                }
-                                _ => {}
-                            }
-                        } else if self.selected_panel_index == PROJECT_INPUT_PANEL_INDEX {
-                            match key_event.code {
-                                KeyCode::Enter => {
-                                    let new_project_name =
-                                        self.project_input.input.value().to_string();
-                                    if self.projects.selected().is_some() {
-                                        let mut project = self.projects.selected().unwrap().clone();
-                                        project.name = new_project_name.clone();
-                                        self.edit_project(project);
-                                    } else {
-                                        self.add_project(new_project_name.clone());
-                                    }
-                                    self.project_input =
-                                        InputComponent::new(new_project_name, InputMode::Normal);
-                                    self.selected_panel_index = PROJECT_LIST_PANEL_INDEX;
-                                    self.projects = StatefulList::with_items(
-                                        self.repository.find_all().to_vec(),
-                                    );
-                                }
-                                KeyCode::Esc => {
-                                    self.project_input.mode = InputMode::Normal;
-                                    if self.projects.selected().is_some() {
-                                        let project_name =
-                                            self.projects.selected().unwrap().name.clone();
-                                        self.project_input =
-                                            InputComponent::new(project_name, InputMode::Normal);
-                                        self.selected_panel_index = PROJECT_LIST_PANEL_INDEX;
-                                    }
-                                }
-                                _ => {
-                                    if self.project_input.mode == InputMode::Editing {
-                                        self.project_input
-                                            .input
-                                            .handle_event(&Event::Key(key_event));
-                                    }
-                                }
-                            }
-                        } else if self.selected_panel_index == TIMER_BUTTONS_PANEL_INDEX {
-                            match key_event.code {
-                                KeyCode::Enter => {
-                                    if self.timer_buttons.selected().is_some()
-                                        && self
-                                            .timer_buttons
-                                            .selected()
-                                            .unwrap()
-                                            .text
-                                            .eq("New project")
-                                    {
-                                        self.selected_panel_index = PROJECT_INPUT_PANEL_INDEX;
-                                        self.project_input =
-                                            InputComponent::new(String::new(), InputMode::Editing);
-                                    }
-                                }
-                                _ => {}
+                    Err(err) => self.error = Some(err.details),
+                }
+                self.reload_projects();
+            }
+            Effect::StopTimer => {
+                match self.repository.stop_timer() {
+                    Ok(_) => {
+                        self.error = None;
+                        self.message = Some("Stopped timer".to_string());
+                    }
+                    Err(err) => self.error = Some(err.details),
+                }
+                self.reload_projects();
+            }
+            Effect::ExportCsv(project_id) => self.export_csv(project_id),
+            // Lists are refreshed in place, so the selection and a name being typed stay.
+            Effect::Reload => match self.repository.refresh() {
+                Ok(true) => {
+                    self.reload_projects();
+                    if self.project_input.mode != InputMode::Editing {
+                        self.project_input = match self.projects.selected() {
+                            Some(project) => {
+                                InputComponent::new(project.name.clone(), InputMode::Normal)
                             }
-                        }
+                            None => InputComponent::default(),
+                        };
                     }
+                    self.message = Some("Reloaded changes made by another program".to_string());
                 }
+                Ok(false) => {}
+                Err(err) => self.error = Some(err.details),
+            },
+        }
+    }
+
+    pub fn running_timer(&self) -> Option<(&Project, &Timer)> {
+        self.projects.items.iter().find_map(|project| {
+            project
+                .timers
+                .iter()
+                .find(|timer| timer.end_time.is_none())
+                .map(|timer| (project, timer))
+        })
+    }
+
+    // Keeps the selected project even when others were added or removed, unlike building a
+    // new list.
+    fn reload_projects(&mut self) {
+        let selected = self.projects.selected().map(|project| project.id);
+        self.projects.items = self.repository.find_all().to_vec();
+        let index = selected.and_then(|id| self.projects.items.iter().position(|x| x.id == id));
+        self.projects.state.select(index);
+
+        let count = self.timer_count();
+        if self.timers.selected().is_some_and(|i| i >= count) {
+            self.timers.select(count.checked_sub(1));
+        }
+    }
+
+    fn export_csv(&mut self, project_id: Option<u64>) {
+        match self.write_csv(project_id) {
+            Ok((count, path)) => {
+                self.error = None;
+                self.message = Some(format!("Exported {} entries to {}", count, path.display()));
             }
+            Err(err) => self.error = Some(err.details),
         }
     }
 
-    pub fn delete_project(&mut self, project_id: u64) {
-        if let Err(err) = self.repository.delete_project(project_id) {
-            self.error = Some(err.details);
+    fn write_csv(&self, project_id: Option<u64>) -> Result<(usize, PathBuf), TimerError> {
+        let now = self.clock.now();
+        let filter = ExportFilter {
+            project: project_id.map(|id| id.to_string()),
+            ..ExportFilter::default()
+        };
+        let file_name = format!("timers-{}.csv", self.zone.format(now, "%Y%m%d-%H%M%S")?);
+        let path = Path::new(self.repository.file_name())
+            .parent()
+            .unwrap_or(Path::new("."))
+            .join(file_name);
+
+        let options = ExportOptions {
+            zone: self.zone.clone(),
+            formats: self.formats.clone(),
+            ..ExportOptions::default()
+        };
+        let report = Report::build(
+            self.repository.find_all(),
+            &filter,
+            &options.zone,
+            &options.rounding,
+            now,
+        )?;
+
+        let file = File::create(&path)
+            .map_err(|e| TimerError::new(&format!("Failed to create {}: {}", path.display(), e)))?;
+        let count = export::csv::write_csv(file, &report, &options)?;
+        Ok((count, path))
+    }
+}
+
+// Index of the list item under the position, for a bordered list scrolled by `offset` items.
+fn list_index(area: Rect, offset: usize, item_height: u16, position: Position) -> Option<usize> {
+    let top = area.y + 1;
+    if position.y < top || position.y >= area.bottom().saturating_sub(1) {
+        return None;
+    }
+    Some(offset + ((position.y - top) / item_height) as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::KeyModifiers;
+
+    use super::*;
+    use crate::{
+        clock::FixedClock,
+        keymap::KeymapPreset,
+        storage::StorageKind,
+        test_support::{DataFile, NOW},
+    };
+
+    fn app_with(name: &str, projects: &[&str], config: Config) -> (App, DataFile) {
+        let file = DataFile::new(name);
+        let config = Config {
+            data_file: file.path(),
+            ..config
+        };
+        let clock = Rc::new(FixedClock::new(NOW));
+        let repository = Repository::open(&config.data_file, config.storage, clock).unwrap();
+        let mut app = App::new(&config, repository);
+        for project in projects {
+            app.perform(Effect::AddProject(project.to_string()));
         }
+        (app, file)
     }
 
-    pub fn edit_project(&mut self, project: Project) {
-        if let Err(err) = self.repository.edit_project(project) {
-            self.error = Some(err.details);
+    fn app(name: &str, projects: &[&str]) -> (App, DataFile) {
+        app_with(name, projects, Config::default())
+    }
+
+    fn type_text(app: &mut App, text: &str) {
+        for c in text.chars() {
+            app.on_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
         }
     }
 
-    pub fn add_project(&mut self, project_name: String) {
-        if let Err(err) = self.repository.add_project(project_name) {
-            self.error = Some(err.details);
+    fn selected_name(app: &mut App) -> Option<String> {
+        app.projects.selected().map(|project| project.name.clone())
+    }
+
+    #[test]
+    fn next_panel_cycles_through_panels() {
+        let (mut app, _file) = app("next_panel", &[]);
+        let mut panels = Vec::new();
+        for _ in 0..3 {
+            app.update(Action::NextPanel);
+            panels.push(app.selected_panel_index);
         }
+        assert_eq!(
+            panels,
+            [
+                PROJECT_LIST_PANEL_INDEX,
+                TIMER_LIST_PANEL_INDEX,
+                TIMER_BUTTONS_PANEL_INDEX
+            ]
+        );
+    }
+
+    #[test]
+    fn moving_in_project_list_selects_project_and_fills_input() {
+        let (mut app, _file) = app("moving", &["Alpha", "Beta"]);
+        app.update(Action::NextPanel);
+
+        app.update(Action::Down);
+        assert_eq!(selected_name(&mut app).as_deref(), Some("Alpha"));
+        app.update(Action::Last);
+        assert_eq!(selected_name(&mut app).as_deref(), Some("Beta"));
+        assert_eq!(app.project_input.input.value(), "Beta");
+        app.update(Action::Down);
+        assert_eq!(selected_name(&mut app).as_deref(), Some("Alpha"));
+    }
+
+    #[test]
+    fn moving_in_empty_lists_selects_nothing() {
+        let (mut app, _file) = app("moving_empty", &[]);
+        app.areas.project_list = Rect::new(0, 0, 10, 10);
+        for _ in 0..2 {
+            app.update(Action::NextPanel);
+            for action in [Action::Down, Action::Up, Action::First, Action::Last] {
+                app.update(action);
+            }
+        }
+        for kind in [MouseEventKind::ScrollDown, MouseEventKind::ScrollUp] {
+            app.on_mouse(MouseEvent {
+                kind,
+                column: 1,
+                row: 1,
+                modifiers: KeyModifiers::NONE,
+            });
+        }
+
+        assert_eq!(app.projects.state.selected(), None);
+        assert_eq!(app.timers.selected(), None);
+    }
+
+    #[test]
+    fn renaming_returns_effect_without_touching_repository() {
+        let (mut app, _file) = app("renaming", &["Alpha"]);
+        app.update(Action::NextPanel);
+        app.update(Action::Down);
+        let project_id = app.projects.items[0].id;
+
+        app.update(Action::EditProject);
+        assert!(app.project_input.mode == InputMode::Editing);
+        type_text(&mut app, "xy");
+
+        let effect = app.update(Action::Confirm);
+        assert_eq!(
+            effect,
+            Some(Effect::RenameProject(project_id, "Alphaxy".to_string()))
+        );
+        assert!(app.project_input.mode == InputMode::Normal);
+        assert_eq!(app.selected_panel_index, PROJECT_LIST_PANEL_INDEX);
+        assert_eq!(app.repository.find_all()[0].name, "Alpha");
+
+        app.perform(effect.unwrap());
+        assert_eq!(app.projects.items[0].name, "Alphaxy");
+        assert_eq!(app.projects.state.selected(), Some(0));
+    }
+
+    #[test]
+    fn reload_keeps_selection_and_edit_in_progress() {
+        let (mut app, file) = app("reload", &["Alpha", "Beta"]);
+        app.update(Action::NextPanel);
+        app.update(Action::Last);
+        app.update(Action::EditProject);
+        type_text(&mut app, "xy");
+        // Nothing changed on disk yet.
+        app.perform(Effect::Reload);
+        assert_eq!(app.message, None);
+
+        let mut other =
+            Repository::open(&file.path(), StorageKind::Json, app.repository.clock()).unwrap();
+        other.delete_project(app.projects.items[0].id).unwrap();
+        app.perform(Effect::Reload);
+
+        assert_eq!(app.projects.items.len(), 1);
+        assert_eq!(selected_name(&mut app).as_deref(), Some("Beta"));
+        assert!(app.project_input.mode == InputMode::Editing);
+        assert_eq!(app.project_input.input.value(), "Betaxy");
+        assert!(app.message.is_some());
+    }
+
+    #[test]
+    fn typed_keys_go_to_input_instead_of_actions() {
+        let (mut app, _file) = app("typed_keys", &["Alpha"]);
+        app.update(Action::NewProject);
+        // `e`, `d` and `x` are bound, but while editing they are just text.
+        type_text(&mut app, "edx");
+
+        assert_eq!(app.project_input.input.value(), "edx");
+        assert!(!app.confirm_dialog_component.confirm_popup.is_opened());
+        assert_eq!(
+            app.update(Action::Confirm),
+            Some(Effect::AddProject("edx".to_string()))
+        );
+    }
+
+    #[test]
+    fn new_project_button_starts_editing_empty_input() {
+        let (mut app, _file) = app("new_project", &["Alpha"]);
+        app.update(Action::NextPanel);
+        app.update(Action::Down);
+        app.update(Action::NextPanel);
+        app.update(Action::NextPanel);
+        app.update(Action::Right);
+
+        assert_eq!(app.update(Action::Confirm), None);
+        assert_eq!(app.selected_panel_index, PROJECT_INPUT_PANEL_INDEX);
+        assert!(app.project_input.mode == InputMode::Editing);
+        assert_eq!(app.project_input.input.value(), "");
+        assert_eq!(selected_name(&mut app), None);
+    }
+
+    #[test]
+    fn cancel_restores_project_name() {
+        let (mut app, _file) = app("cancel", &["Alpha"]);
+        app.update(Action::NextPanel);
+        app.update(Action::Down);
+        app.update(Action::EditProject);
+        type_text(&mut app, "zz");
+
+        assert_eq!(app.update(Action::Cancel), None);
+        assert_eq!(app.project_input.input.value(), "Alpha");
+        assert_eq!(app.selected_panel_index, PROJECT_LIST_PANEL_INDEX);
+    }
+
+    #[test]
+    fn starting_timer_needs_selected_project() {
+        let (mut app, _file) = app("start_without_project", &["Alpha"]);
+
+        assert_eq!(app.update(Action::StartTimer), None);
+        assert_eq!(app.error.as_deref(), Some("No project selected."));
+    }
+
+    #[test]
+    fn start_and_stop_timer() {
+        let (mut app, _file) = app("start_stop", &["Alpha", "Beta"]);
+        app.update(Action::NextPanel);
+        app.update(Action::Last);
+        let project_id = app.projects.items[1].id;
+
+        assert_eq!(
+            app.update(Action::StartTimer),
+            Some(Effect::StartTimer(project_id))
+        );
+        app.dispatch(Action::StartTimer);
+        let (project, _) = app.running_timer().unwrap();
+        assert_eq!(project.name, "Beta");
+        assert_eq!(app.message.as_deref(), Some("Started timer on Beta"));
+        assert_eq!(selected_name(&mut app).as_deref(), Some("Beta"));
+
+        app.dispatch(Action::StartTimer);
+        assert_eq!(
+            app.error.as_deref(),
+            Some("Timer is already running on this project.")
+        );
+
+        app.dispatch(Action::StopTimer);
+        assert!(app.running_timer().is_none());
+        app.dispatch(Action::StopTimer);
+        assert_eq!(app.error.as_deref(), Some("No timer is running."));
+    }
+
+    #[test]
+    fn help_blocks_other_actions() {
+        let (mut app, _file) = app("help", &["Alpha"]);
+        app.update(Action::NextPanel);
+        app.update(Action::Help);
+        assert!(app.show_help);
+
+        app.update(Action::Down);
+        assert_eq!(selected_name(&mut app), None);
+        app.update(Action::Cancel);
+        assert!(!app.show_help);
+        app.update(Action::Down);
+        assert_eq!(selected_name(&mut app).as_deref(), Some("Alpha"));
+    }
+
+    #[test]
+    fn delete_asks_for_confirmation() {
+        let (mut app, _file) = app("delete", &["Alpha"]);
+        app.update(Action::NextPanel);
+        app.update(Action::Down);
+
+        assert_eq!(app.update(Action::DeleteProject), None);
+        assert!(app.confirm_dialog_component.confirm_popup.is_opened());
+
+        // "No" is selected first.
+        app.on_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert!(!app.confirm_dialog_component.confirm_popup.is_opened());
+        assert_eq!(app.projects.items.len(), 1);
+    }
+
+    #[test]
+    fn export_works_on_selected_project() {
+        let (mut app, _file) = app("export", &["Alpha"]);
+        assert_eq!(app.update(Action::ExportCsv), None);
+
+        app.update(Action::NextPanel);
+        assert_eq!(app.update(Action::ExportCsv), Some(Effect::ExportCsv(None)));
+        app.update(Action::Down);
+        let project_id = app.projects.items[0].id;
+        assert_eq!(
+            app.update(Action::ExportCsv),
+            Some(Effect::ExportCsv(Some(project_id)))
+        );
+    }
+
+    #[test]
+    fn palette_runs_matching_command() {
+        let (mut app, _file) = app("palette", &["Alpha", "Beta"]);
+        app.update(Action::CommandPalette);
+        type_text(&mut app, "start bet");
+        let project_id = app.projects.items[1].id;
+
+        assert_eq!(
+            app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)),
+            Some(Effect::StartTimer(project_id))
+        );
+        assert!(app.palette.is_none());
+        assert_eq!(selected_name(&mut app).as_deref(), Some("Beta"));
+    }
+
+    #[test]
+    fn palette_closes_on_cancel() {
+        let (mut app, _file) = app("palette_cancel", &[]);
+        app.update(Action::CommandPalette);
+        type_text(&mut app, "q");
+        app.on_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+
+        assert!(app.palette.is_none());
+        assert!(!app.should_quit);
+    }
+
+    #[test]
+    fn key_sequences_resolve_to_actions() {
+        let config = Config {
+            keymap: KeymapPreset::Vim,
+            ..Config::default()
+        };
+        let (mut app, _file) = app_with("sequences", &["Alpha", "Beta"], config);
+        app.update(Action::NextPanel);
+        type_text(&mut app, "G");
+        assert_eq!(selected_name(&mut app).as_deref(), Some("Beta"));
+        type_text(&mut app, "gg");
+        assert_eq!(selected_name(&mut app).as_deref(), Some("Alpha"));
+
+        app.on_key(KeyEvent::new(KeyCode::Char('q'), KeyModifiers::CONTROL));
+        assert!(app.should_quit);
     }
-}
-fn char_to_key_event(c: char) -> KeyEvent {
-    KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)
 }
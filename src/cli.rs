@@ -0,0 +1,608 @@
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::PathBuf,
+    rc::Rc,
+};
+
+use chrono::{Datelike, NaiveDate};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use crate::{
+    clock::SystemClock,
+    config::Config,
+    datetime::{parse_date_time_format, ClockFormat, DurationFormat, Formats, Zone},
+    error::TimerError,
+    export::{self, invoice::InvoiceOptions, ExportFilter, ExportOptions},
+    import::{self, DateOrder, ImportSummary, ImportedEntry},
+    model::{
+        billing::Cents,
+        project::Project,
+        rounding::{Rounding, RoundingMode, RoundingScope},
+    },
+    report::Report,
+    repository::Repository,
+    storage::{self, StorageKind},
+};
+
+#[derive(Parser)]
+#[command(version, about = "Track time spent on projects.")]
+pub struct Cli {
+    /// Path to the config file; `timers-rs/config.toml` in the user config directory
+    /// is used when omitted.
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Path to the projects data file.
+    #[arg(long, global = true)]
+    pub file: Option<String>,
+
+    /// Storage backend of the data file: json or sqlite.
+    #[arg(long, global = true, value_enum)]
+    pub storage: Option<StorageKind>,
+
+    /// Time zone used for dates and day boundaries: "local", "UTC" or an IANA name
+    /// like "Europe/Belgrade".
+    #[arg(long, global = true)]
+    pub timezone: Option<Zone>,
+
+    /// strftime pattern used for dates and times, e.g. "%d.%m.%Y %H:%M".
+    #[arg(long, global = true, value_parser = parse_date_time_format)]
+    pub date_format: Option<String>,
+
+    /// Clock used for times: 12 or 24.
+    #[arg(long, global = true)]
+    pub clock: Option<ClockFormat>,
+
+    /// Duration style: hms (01:23:45), hm (01:23), short (1h 23m) or decimal (1.38h).
+    #[arg(long, global = true)]
+    pub duration_format: Option<DurationFormat>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Export timer entries.
+    #[command(subcommand)]
+    Export(ExportCommand),
+    /// Import timer entries exported by other time trackers.
+    Import(ImportArgs),
+    /// Set hourly rate, currency and billable flag of a project or a single timer.
+    Billing(BillingArgs),
+    /// Set rounding of billed durations for a project, overriding the one given to exports.
+    Rounding(ProjectRoundingArgs),
+    /// Copy all projects and timers to a new data file of another storage backend.
+    Migrate(MigrateArgs),
+    /// Show the recorded changes to projects and timers, oldest first.
+    History(HistoryArgs),
+}
+
+#[derive(Subcommand)]
+pub enum ExportCommand {
+    /// Export timer entries as CSV rows.
+    Csv(CsvArgs),
+    /// Export closed timers as iCalendar events.
+    Ics(IcsArgs),
+    /// Export a timesheet of days and projects with daily and weekly totals.
+    Timesheet(TimesheetArgs),
+    /// Export an invoice of billable timers.
+    Invoice(InvoiceArgs),
+}
+
+#[derive(Args)]
+pub struct FilterArgs {
+    /// First day to include (YYYY-MM-DD).
+    #[arg(long)]
+    pub from: Option<NaiveDate>,
+
+    /// Last day to include (YYYY-MM-DD).
+    #[arg(long)]
+    pub to: Option<NaiveDate>,
+
+    /// Only export timers of the project with given name or id.
+    #[arg(long)]
+    pub project: Option<String>,
+
+    /// Output file; standard output is used when omitted.
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct RoundingArgs {
+    /// Rounding of billed durations: none, nearest, up or down.
+    #[arg(long = "rounding")]
+    pub rounding_mode: Option<RoundingMode>,
+
+    /// Rounding increment in minutes.
+    #[arg(long)]
+    pub rounding_increment: Option<u32>,
+
+    /// Round each entry or the daily total of each project: entry or day.
+    #[arg(long)]
+    pub rounding_scope: Option<RoundingScope>,
+}
+
+impl Cli {
+    // Options given on the command line take precedence over the config file.
+    pub fn config(&self) -> Result<Config, TimerError> {
+        let mut config = Config::load(self.config.as_deref())?;
+        if let Some(file) = &self.file {
+            config.data_file = file.clone();
+        }
+        if let Some(storage) = self.storage {
+            config.storage = storage;
+        }
+        if let Some(timezone) = &self.timezone {
+            config.timezone = timezone.clone();
+        }
+        if self.date_format.is_some() {
+            config.date_format = self.date_format.clone();
+        }
+        if let Some(clock) = self.clock {
+            config.clock = clock;
+        }
+        if let Some(duration_format) = self.duration_format {
+            config.duration_format = duration_format;
+        }
+        Ok(config)
+    }
+}
+
+impl RoundingArgs {
+    fn rounding(&self, default: &Rounding) -> Rounding {
+        Rounding {
+            mode: self.rounding_mode.unwrap_or(default.mode),
+            increment: self.rounding_increment.unwrap_or(default.increment),
+            scope: self.rounding_scope.unwrap_or(default.scope),
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct CsvArgs {
+    #[command(flatten)]
+    pub filter: FilterArgs,
+
+    #[command(flatten)]
+    pub rounding: RoundingArgs,
+}
+
+#[derive(Args)]
+pub struct IcsArgs {
+    #[command(flatten)]
+    pub filter: FilterArgs,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum DocumentFormat {
+    Markdown,
+    Html,
+}
+
+#[derive(Args)]
+pub struct TimesheetArgs {
+    #[command(flatten)]
+    pub filter: FilterArgs,
+
+    #[command(flatten)]
+    pub rounding: RoundingArgs,
+
+    /// Output document format.
+    #[arg(long, value_enum, default_value_t = DocumentFormat::Markdown)]
+    pub format: DocumentFormat,
+}
+
+#[derive(Args)]
+pub struct InvoiceArgs {
+    #[command(flatten)]
+    pub filter: FilterArgs,
+
+    #[command(flatten)]
+    pub rounding: RoundingArgs,
+
+    /// Output document format.
+    #[arg(long, value_enum, default_value_t = DocumentFormat::Markdown)]
+    pub format: DocumentFormat,
+
+    /// Invoice number; next number from the data file is used when omitted.
+    #[arg(long)]
+    pub number: Option<String>,
+
+    /// Prefix of generated invoice numbers.
+    #[arg(long)]
+    pub prefix: Option<String>,
+
+    /// Sender address line, may be repeated; replaces the one from the config file.
+    #[arg(long)]
+    pub sender: Vec<String>,
+
+    /// Client address line, may be repeated; replaces the one from the config file.
+    #[arg(long)]
+    pub client: Vec<String>,
+
+    /// Number of days until the invoice is due.
+    #[arg(long)]
+    pub due_days: Option<u32>,
+}
+
+#[derive(Args)]
+pub struct BillingArgs {
+    /// Name or id of the project.
+    pub project: String,
+
+    /// Set the override on the timer with given id instead of the project.
+    #[arg(long)]
+    pub timer: Option<u32>,
+
+    /// Hourly rate, with at most two decimals.
+    #[arg(long)]
+    pub rate: Option<Cents>,
+
+    /// Currency code shown next to amounts, e.g. "EUR".
+    #[arg(long)]
+    pub currency: Option<String>,
+
+    /// Whether the time is billed to the client.
+    #[arg(long)]
+    pub billable: Option<bool>,
+}
+
+#[derive(Args)]
+pub struct ProjectRoundingArgs {
+    /// Name or id of the project.
+    pub project: String,
+
+    #[command(flatten)]
+    pub rounding: RoundingArgs,
+
+    /// Remove the project rounding, so the one given to exports is used again.
+    #[arg(long)]
+    pub clear: bool,
+}
+
+#[derive(Args)]
+pub struct MigrateArgs {
+    /// Storage backend of the new data file.
+    #[arg(long, value_enum)]
+    pub to: StorageKind,
+
+    /// New data file; it must not exist yet.
+    pub output: PathBuf,
+}
+
+#[derive(Args)]
+pub struct HistoryArgs {
+    /// Only show the last given number of changes.
+    #[arg(long)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ImportFormat {
+    /// Toggl Track detailed CSV export.
+    Toggl,
+    /// Clockify detailed CSV report (read the same way as Toggl exports).
+    Clockify,
+    /// Timewarrior data file or data directory.
+    Timewarrior,
+}
+
+#[derive(Args)]
+pub struct ImportArgs {
+    /// Format of the imported data.
+    pub format: ImportFormat,
+
+    /// File (or Timewarrior data directory) to import.
+    pub path: PathBuf,
+
+    /// Only show what would be imported, without saving.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Order of day and month in CSV dates like 03/04/2024, detected from the file by default.
+    #[arg(long, value_enum)]
+    pub date_order: Option<DateOrder>,
+}
+
+impl ImportArgs {
+    fn read(&self, zone: &Zone) -> Result<Vec<ImportedEntry>, TimerError> {
+        let open = || {
+            File::open(&self.path).map_err(|e| {
+                TimerError::new(&format!("Failed to open {}: {}", self.path.display(), e))
+            })
+        };
+        match self.format {
+            ImportFormat::Toggl | ImportFormat::Clockify => {
+                import::csv::read(open()?, zone, self.date_order)
+            }
+            ImportFormat::Timewarrior => import::timewarrior::read_path(&self.path),
+        }
+    }
+}
+
+impl FilterArgs {
+    fn filter(&self) -> ExportFilter {
+        ExportFilter {
+            from: self.from,
+            to: self.to,
+            project: self.project.clone(),
+        }
+    }
+
+    fn options(&self, zone: &Zone, formats: &Formats, rounding: Rounding) -> ExportOptions {
+        ExportOptions {
+            zone: zone.clone(),
+            formats: formats.clone(),
+            rounding,
+        }
+    }
+
+    fn writer(&self) -> Result<Box<dyn Write>, TimerError> {
+        match &self.output {
+            Some(path) => File::create(path)
+                .map(|file| Box::new(file) as Box<dyn Write>)
+                .map_err(|e| {
+                    TimerError::new(&format!("Failed to create {}: {}", path.display(), e))
+                }),
+            None => Ok(Box::new(io::stdout())),
+        }
+    }
+}
+
+pub fn execute(command: Command, config: &Config) -> Result<(), TimerError> {
+    let mut repository =
+        Repository::open(&config.data_file, config.storage, Rc::new(SystemClock))?;
+    let zone = &config.timezone;
+    let formats = &config.formats();
+    match command {
+        Command::Export(ExportCommand::Csv(args)) => {
+            let filter = args.filter.filter();
+            let options =
+                args.filter
+                    .options(zone, formats, args.rounding.rounding(&config.rounding));
+            let report = build_report(&repository, &filter, &options)?;
+            let count = export::csv::write_csv(args.filter.writer()?, &report, &options)?;
+            eprintln!("Exported {} timer entries.", count);
+        }
+        Command::Export(ExportCommand::Ics(args)) => {
+            let filter = args.filter.filter();
+            let count = export::ics::write_ics(
+                args.filter.writer()?,
+                &find_projects(&repository, &filter, zone)?,
+                &filter,
+                &args.filter.options(zone, formats, Rounding::default()),
+                repository.now(),
+            )?;
+            eprintln!("Exported {} calendar events.", count);
+        }
+        Command::Export(ExportCommand::Timesheet(args)) => {
+            let filter = args.filter.filter();
+            let options =
+                args.filter
+                    .options(zone, formats, args.rounding.rounding(&config.rounding));
+            let report = build_report(&repository, &filter, &options)?;
+            let writer = args.filter.writer()?;
+            match args.format {
+                DocumentFormat::Markdown => {
+                    export::timesheet::write_markdown(writer, &report, &filter, &options)?
+                }
+                DocumentFormat::Html => {
+                    export::timesheet::write_html(writer, &report, &filter, &options)?
+                }
+            }
+        }
+        Command::Export(ExportCommand::Invoice(args)) => {
+            let filter = args.filter.filter();
+            let options =
+                args.filter
+                    .options(zone, formats, args.rounding.rounding(&config.rounding));
+            let report = build_report(&repository, &filter, &options)?;
+            let issue_date = zone.date(repository.now())?;
+            let write_invoice = |number: String| {
+                let options = InvoiceOptions {
+                    number,
+                    issue_date,
+                    due_date: args
+                        .due_days
+                        .or(config.invoice.due_days)
+                        .map(|days| issue_date + chrono::Days::new(days.into())),
+                    date_format: options.formats.date(),
+                    sender: or_config(args.sender.clone(), &config.invoice.sender),
+                    client: or_config(args.client.clone(), &config.invoice.client),
+                };
+                let writer = args.filter.writer()?;
+                match args.format {
+                    DocumentFormat::Markdown => {
+                        export::invoice::write_markdown(writer, &report, &options)?
+                    }
+                    DocumentFormat::Html => {
+                        export::invoice::write_html(writer, &report, &options)?
+                    }
+                }
+                eprintln!("Created invoice {}.", options.number);
+                Ok(())
+            };
+            match &args.number {
+                Some(number) => write_invoice(number.clone())?,
+                None => repository.issue_invoice(|counter| {
+                    write_invoice(format!(
+                        "{}{}-{:04}",
+                        args.prefix.as_ref().unwrap_or(&config.invoice.prefix),
+                        issue_date.year(),
+                        counter
+                    ))
+                })?,
+            }
+        }
+        Command::Billing(args) => set_billing(&mut repository, &args)?,
+        Command::Rounding(args) => {
+            let project_id = find_project(&repository, &args.project)?.id;
+            repository.update_project(project_id, |project| {
+                project.rounding = (!args.clear).then(|| args.rounding.rounding(&config.rounding));
+                Ok(())
+            })?;
+        }
+        Command::Import(args) => {
+            let summary = repository.import(args.read(zone)?, args.dry_run)?;
+            print_import_summary(&summary, zone, formats, args.dry_run)?;
+        }
+        Command::Migrate(args) => migrate(&repository, &args, config)?,
+        Command::History(args) => {
+            let history = repository.history()?;
+            let skip = args
+                .limit
+                .map_or(0, |limit| history.len().saturating_sub(limit));
+            for entry in &history[skip..] {
+                println!(
+                    "{}  {}  {}",
+                    zone.format(entry.time, &formats.date_time)?,
+                    entry.user.as_deref().unwrap_or("-"),
+                    entry.event.describe(repository.find_all())
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+fn build_report(
+    repository: &Repository,
+    filter: &ExportFilter,
+    options: &ExportOptions,
+) -> Result<Report, TimerError> {
+    Report::build(
+        &find_projects(repository, filter, &options.zone)?,
+        filter,
+        &options.zone,
+        &options.rounding,
+        repository.now(),
+    )
+}
+
+// With a date range only the timers around it are loaded from the storage; the filter still
+// decides which days they count for.
+fn find_projects(
+    repository: &Repository,
+    filter: &ExportFilter,
+    zone: &Zone,
+) -> Result<Vec<Project>, TimerError> {
+    if filter.from.is_none() && filter.to.is_none() {
+        return Ok(repository.find_all().clone());
+    }
+    let (from, to) = filter.time_range(zone);
+    repository.find_between(from, to)
+}
+
+fn migrate(repository: &Repository, args: &MigrateArgs, config: &Config) -> Result<(), TimerError> {
+    if args.output.exists() {
+        return Err(TimerError::new(&format!(
+            "{} already exists.",
+            args.output.display()
+        )));
+    }
+    let mut storage = storage::open(args.to, &args.output, repository.clock())?;
+    repository.copy_to(storage.as_mut())?;
+
+    let projects = repository.find_all();
+    eprintln!(
+        "Copied {} projects and {} timers from {} ({}) to {} ({}).",
+        projects.len(),
+        projects.iter().map(|x| x.timers.len()).sum::<usize>(),
+        config.data_file,
+        config.storage,
+        args.output.display(),
+        args.to
+    );
+    Ok(())
+}
+
+fn find_project(repository: &Repository, name: &str) -> Result<Project, TimerError> {
+    repository
+        .find_all()
+        .iter()
+        .find(|x| x.name.eq_ignore_ascii_case(name) || x.id.to_string() == name)
+        .cloned()
+        .ok_or_else(|| TimerError::new(&format!("Project {} does not exists.", name)))
+}
+
+fn set_billing(repository: &mut Repository, args: &BillingArgs) -> Result<(), TimerError> {
+    let project_id = find_project(repository, &args.project)?.id;
+    repository.update_project(project_id, |project| set_project_billing(project, args))?;
+    Ok(())
+}
+
+fn set_project_billing(project: &mut Project, args: &BillingArgs) -> Result<(), TimerError> {
+    let billing = match args.timer {
+        Some(timer_id) => {
+            &mut project
+                .timers
+                .iter_mut()
+                .find(|x| x.id == timer_id)
+                .ok_or_else(|| TimerError::new(&format!("Timer {} does not exists.", timer_id)))?
+                .billing
+        }
+        None => &mut project.billing,
+    };
+    if args.rate.is_some() {
+        billing.hourly_rate = args.rate;
+    }
+    if args.currency.is_some() {
+        billing.currency = args.currency.clone();
+    }
+    if args.billable.is_some() {
+        billing.billable = args.billable;
+    }
+    Ok(())
+}
+
+fn print_import_summary(
+    summary: &ImportSummary,
+    zone: &Zone,
+    formats: &Formats,
+    dry_run: bool,
+) -> Result<(), TimerError> {
+    if dry_run {
+        for project in &summary.created_projects {
+            println!("New project: {}", project);
+        }
+        for entry in &summary.added {
+            println!(
+                "{}: {} - {}{}",
+                entry.project,
+                zone.format(entry.start_time, &formats.date_time)?,
+                entry
+                    .end_time
+                    .map(|end| zone.format(end, &formats.date_time))
+                    .transpose()?
+                    .unwrap_or_default(),
+                entry
+                    .note
+                    .as_ref()
+                    .map(|note| format!(" ({})", note))
+                    .unwrap_or_default()
+            );
+        }
+    }
+
+    println!(
+        "{} {} projects, {} {} timers, skipped {} duplicates and {} running timers.",
+        if dry_run { "Would create" } else { "Created" },
+        summary.created_projects.len(),
+        if dry_run { "would add" } else { "added" },
+        summary.added.len(),
+        summary.duplicates,
+        summary.running
+    );
+    Ok(())
+}
+
+fn or_config(lines: Vec<String>, config: &[String]) -> Vec<String> {
+    if lines.is_empty() {
+        config.to_vec()
+    } else {
+        lines
+    }
+}
@@ -0,0 +1,46 @@
+use std::{
+    cell::Cell,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// Source of the current time, so code depending on it can run with fixed times.
+pub trait Clock {
+    // Seconds since the Unix epoch.
+    fn now(&self) -> u64;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Time went backwards")
+            .as_secs()
+    }
+}
+
+// Stands still until it is set or advanced.
+#[derive(Debug, Clone, Default)]
+pub struct FixedClock(Cell<u64>);
+
+impl FixedClock {
+    pub fn new(now: u64) -> Self {
+        Self(Cell::new(now))
+    }
+
+    pub fn set(&self, now: u64) {
+        self.0.set(now);
+    }
+
+    pub fn advance(&self, seconds: u64) {
+        self.0.set(self.0.get() + seconds);
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> u64 {
+        self.0.get()
+    }
+}
@@ -0,0 +1,243 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use serde::{Deserialize, Deserializer};
+
+use crate::{
+    action::Action,
+    datetime::{parse_date_time_format, ClockFormat, DurationFormat, Formats, Zone},
+    error::TimerError,
+    keymap::{check_bindings, KeyBindings, Keymap, KeymapPreset},
+    model::rounding::Rounding,
+    storage::StorageKind,
+    theme::{Theme, ThemeConfig, DEFAULT_THEME},
+};
+
+const DEFAULT_DATA_FILE: &str = "projects.json";
+const DEFAULT_TICK_RATE_MS: u64 = 250;
+const DEFAULT_INVOICE_PREFIX: &str = "INV-";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub data_file: String,
+    pub storage: StorageKind,
+    pub tick_rate_ms: u64,
+    pub mouse: bool,
+    pub big_timer: bool,
+    #[serde(deserialize_with = "from_str")]
+    pub timezone: Zone,
+    #[serde(deserialize_with = "date_time_format")]
+    pub date_format: Option<String>,
+    #[serde(deserialize_with = "from_str")]
+    pub clock: ClockFormat,
+    #[serde(deserialize_with = "from_str")]
+    pub duration_format: DurationFormat,
+    pub rounding: Rounding,
+    pub invoice: InvoiceConfig,
+    pub keymap: KeymapPreset,
+    pub keys: BTreeMap<Action, KeyBindings>,
+    pub theme: String,
+    pub themes: BTreeMap<String, ThemeConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InvoiceConfig {
+    pub prefix: String,
+    pub sender: Vec<String>,
+    pub client: Vec<String>,
+    pub due_days: Option<u32>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            data_file: Self::default_data_file(),
+            storage: StorageKind::default(),
+            tick_rate_ms: DEFAULT_TICK_RATE_MS,
+            mouse: true,
+            big_timer: false,
+            timezone: Zone::Local,
+            date_format: None,
+            clock: ClockFormat::default(),
+            duration_format: DurationFormat::default(),
+            rounding: Rounding::default(),
+            invoice: InvoiceConfig::default(),
+            keymap: KeymapPreset::default(),
+            keys: BTreeMap::new(),
+            theme: DEFAULT_THEME.to_string(),
+            themes: BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for InvoiceConfig {
+    fn default() -> Self {
+        Self {
+            prefix: DEFAULT_INVOICE_PREFIX.to_string(),
+            sender: Vec::new(),
+            client: Vec::new(),
+            due_days: None,
+        }
+    }
+}
+
+impl Config {
+    // `$XDG_CONFIG_HOME/timers-rs/config.toml`, or the platform equivalent.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("timers-rs").join("config.toml"))
+    }
+
+    // `$XDG_DATA_HOME/timers-rs/projects.json`, or the platform equivalent.
+    fn default_data_file() -> String {
+        dirs::data_dir()
+            .map(|dir| dir.join("timers-rs").join(DEFAULT_DATA_FILE))
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_DATA_FILE))
+            .to_string_lossy()
+            .to_string()
+    }
+
+    // The data file's directory, like the default one, is created on first use.
+    pub fn create_data_dir(&self) -> Result<(), TimerError> {
+        match Path::new(&self.data_file).parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => fs::create_dir_all(dir).map_err(|e| {
+                TimerError::new(&format!("Failed to create {}: {}", dir.display(), e))
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    // Missing file at the default location just means defaults, an explicitly given
+    // one has to exist.
+    pub fn load(path: Option<&Path>) -> Result<Self, TimerError> {
+        let (path, required) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => match Self::default_path() {
+                Some(path) => (path, false),
+                None => return Ok(Self::default()),
+            },
+        };
+        if !required && !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&path)
+            .map_err(|e| TimerError::new(&format!("Failed to read {}: {}", path.display(), e)))?;
+        Self::parse(&content)
+            .map_err(|e| TimerError::new(&format!("Invalid config {}: {}", path.display(), e)))
+    }
+
+    pub fn parse(content: &str) -> Result<Self, TimerError> {
+        let config: Self =
+            toml::from_str(content).map_err(|e| TimerError::new(e.to_string().trim_end()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), TimerError> {
+        if self.data_file.is_empty() {
+            return Err(TimerError::new("data_file must not be empty."));
+        }
+        if self.tick_rate_ms == 0 {
+            return Err(TimerError::new("tick_rate_ms must be greater than 0."));
+        }
+        check_bindings(&self.keys)?;
+        for theme in std::iter::once(&self.theme).chain(self.themes.keys()) {
+            Theme::resolve(theme, &self.themes)?;
+        }
+        Ok(())
+    }
+
+    pub fn tick_rate(&self) -> Duration {
+        Duration::from_millis(self.tick_rate_ms)
+    }
+
+    pub fn formats(&self) -> Formats {
+        Formats::new(self.date_format.clone(), self.clock, self.duration_format)
+    }
+
+    pub fn keymap(&self) -> Keymap {
+        let mut keymap = Keymap::preset(self.keymap);
+        for (action, bindings) in &self.keys {
+            keymap.bind(*action, bindings);
+        }
+        keymap
+    }
+
+    pub fn theme(&self) -> Theme {
+        let no_color = std::env::var_os("NO_COLOR");
+        Theme::select(&self.theme, &self.themes, no_color.as_deref())
+    }
+}
+
+fn from_str<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    // Lets numbers like `clock = 12` be written without quotes.
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Value {
+        String(String),
+        Number(i64),
+    }
+
+    let value = match Value::deserialize(deserializer)? {
+        Value::String(value) => value,
+        Value::Number(value) => value.to_string(),
+    };
+    value.parse().map_err(serde::de::Error::custom)
+}
+
+fn date_time_format<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    parse_date_time_format(&String::deserialize(deserializer)?)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_file_gives_defaults() {
+        let config = Config::parse("").unwrap();
+        assert!(Path::new(&config.data_file).ends_with("timers-rs/projects.json"));
+        assert_eq!(config.storage, StorageKind::Json);
+        assert_eq!(config.tick_rate(), Duration::from_millis(250));
+        assert_eq!(config.theme, DEFAULT_THEME);
+        assert_eq!(config.invoice.prefix, "INV-");
+        assert_eq!(config.formats(), Formats::default());
+    }
+
+    #[test]
+    fn unknown_keys_are_refused() {
+        let error = Config::parse("tick_rate = 100").unwrap_err();
+        assert!(error.details.contains("unknown field `tick_rate`"));
+        assert!(Config::parse("[invoice]\nnumber = 1").is_err());
+    }
+
+    #[test]
+    fn invalid_values_are_refused() {
+        for (content, message) in [
+            ("data_file = \"\"", "data_file must not be empty."),
+            ("tick_rate_ms = 0", "tick_rate_ms must be greater than 0."),
+            ("theme = \"neon\"", "neon"),
+        ] {
+            let error = Config::parse(content).unwrap_err();
+            assert!(error.details.contains(message), "{}", error.details);
+        }
+    }
+}
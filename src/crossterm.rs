@@ -1,35 +1,48 @@
-use std::{io::{self, Result}, time::{Duration, Instant}};
+use std::{io::{self, Result}, path::Path};
 
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event},
+    event::{DisableMouseCapture, EnableMouseCapture},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{prelude::{Backend, CrosstermBackend}, Terminal};
 
-use crate::{app::App, ui};
+use crate::{
+    app::{App, Effect},
+    config::Config,
+    event::{AppEvent, Events},
+    repository::Repository,
+    ui,
+};
 
 
-pub fn run() -> Result<()> {
+pub fn run(config: &Config, repository: Repository) -> Result<()> {
     install_panic_hook();
 
     enable_raw_mode()?;
     let mut stdout = std::io::stdout();
 
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(stdout, EnterAlternateScreen)?;
+    // Capturing the mouse takes text selection away from the terminal, so it is optional.
+    if config.mouse {
+        execute!(stdout, EnableMouseCapture)?;
+    }
 
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let app = App::new("/mnt/c/tmp/projects.json");
-    let app_result = run_app(&mut terminal, app, Duration::from_millis(250));
+    let mut app = App::new(config, repository);
+    let mut events = Events::new(config.tick_rate());
+    if let Err(err) = events.watch(Path::new(&config.data_file)) {
+        app.error = Some(format!("Not watching the data file for changes: {}", err));
+    }
+    let app_result = run_app(&mut terminal, app, events);
 
     disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
+    if config.mouse {
+        execute!(terminal.backend_mut(), DisableMouseCapture)?;
+    }
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
     terminal.show_cursor()?;
 
     if let Err(err) = app_result {
@@ -42,22 +55,23 @@ pub fn run() -> Result<()> {
 fn run_app<B: Backend>(
     terminal: &mut Terminal<B>,
     mut app: App,
-    tick_rate: Duration,
+    mut events: Events,
 ) -> io::Result<()> {
-    let last_tick = Instant::now();
+    terminal.draw(|frame| ui::draw(frame, &mut app))?;
     loop {
-        terminal.draw(|frame| ui::draw(frame, &mut app))?;
-
-        let timeout = tick_rate.saturating_sub(last_tick.elapsed());
-        if event::poll(timeout)? {
-            if let Event::Key(key_event) = event::read()? {
-                app.on_key(key_event);                
-            }
+        // Every event redraws: ticks move the clocks, input changes the state and a
+        // resize needs a new layout.
+        match events.wait()? {
+            AppEvent::Tick | AppEvent::Resize(..) => {}
+            AppEvent::Key(key_event) => app.on_key(key_event),
+            AppEvent::Mouse(mouse_event) => app.on_mouse(mouse_event),
+            AppEvent::DataChanged => app.perform(Effect::Reload),
         }
-        
+
         if app.should_quit {
             return Ok(());
         }
+        terminal.draw(|frame| ui::draw(frame, &mut app))?;
     }
 }
 
@@ -66,7 +80,7 @@ fn install_panic_hook() {
     let original_hook = std::panic::take_hook();
     std::panic::set_hook(Box::new(move |info| {
         disable_raw_mode().unwrap();
-        execute!(io::stdout(), LeaveAlternateScreen).unwrap();
+        execute!(io::stdout(), DisableMouseCapture, LeaveAlternateScreen).unwrap();
         original_hook(info);
         std::process::exit(1);
     }));
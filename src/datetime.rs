@@ -0,0 +1,306 @@
+use std::str::FromStr;
+
+use chrono::{
+    format::{Item, StrftimeItems},
+    DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc,
+};
+use chrono_tz::Tz;
+
+use crate::error::TimerError;
+
+pub const DEFAULT_DATE_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+const DEFAULT_DATE_TIME_FORMAT_12H: &str = "%Y-%m-%d %I:%M:%S %p";
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Zone {
+    Local,
+    Utc,
+    Named(Tz),
+}
+
+impl FromStr for Zone {
+    type Err = TimerError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "local" => Ok(Zone::Local),
+            "utc" => Ok(Zone::Utc),
+            _ => value
+                .parse::<Tz>()
+                .map(Zone::Named)
+                .map_err(|_| TimerError::new(&format!("Unknown time zone: {}.", value))),
+        }
+    }
+}
+
+impl Zone {
+    pub fn format(&self, timestamp: u64, format: &str) -> Result<String, TimerError> {
+        let utc = to_utc(timestamp)?;
+        Ok(match self {
+            Zone::Local => utc.with_timezone(&Local).format(format).to_string(),
+            Zone::Utc => utc.format(format).to_string(),
+            Zone::Named(tz) => utc.with_timezone(tz).format(format).to_string(),
+        })
+    }
+
+    pub fn date(&self, timestamp: u64) -> Result<NaiveDate, TimerError> {
+        let utc = to_utc(timestamp)?;
+        Ok(match self {
+            Zone::Local => utc.with_timezone(&Local).date_naive(),
+            Zone::Utc => utc.date_naive(),
+            Zone::Named(tz) => utc.with_timezone(tz).date_naive(),
+        })
+    }
+
+    pub fn timestamp(&self, date_time: NaiveDateTime) -> Option<u64> {
+        let timestamp = match self {
+            Zone::Local => Local
+                .from_local_datetime(&date_time)
+                .earliest()?
+                .timestamp(),
+            Zone::Utc => Utc.from_utc_datetime(&date_time).timestamp(),
+            Zone::Named(tz) => tz.from_local_datetime(&date_time).earliest()?.timestamp(),
+        };
+        u64::try_from(timestamp).ok()
+    }
+
+    // Midnight does not exist in zones that switch to daylight saving time at 00:00,
+    // the first valid hour of the day is its start then.
+    pub fn start_of_day(&self, date: NaiveDate) -> u64 {
+        (0..24)
+            .find_map(|hour| self.timestamp(date.and_hms_opt(hour, 0, 0)?))
+            .unwrap_or_else(|| {
+                Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap())
+                    .timestamp() as u64
+            })
+    }
+
+    // Splits interval at local midnights, so days shortened or extended by DST
+    // transitions get exactly the time that belongs to them.
+    pub fn split_by_day(
+        &self,
+        start: u64,
+        end: u64,
+    ) -> Result<Vec<(NaiveDate, u64, u64)>, TimerError> {
+        to_utc(end)?;
+        let mut segments = Vec::new();
+        let mut segment_start = start;
+        loop {
+            let date = self.date(segment_start)?;
+            let next_day = self.start_of_day(date.succ_opt().ok_or_else(|| invalid(end))?);
+            if end <= next_day {
+                segments.push((date, segment_start, end));
+                return Ok(segments);
+            }
+            segments.push((date, segment_start, next_day));
+            segment_start = next_day;
+        }
+    }
+}
+
+// Timestamps come from data files, which may have any number in them.
+pub fn to_utc(timestamp: u64) -> Result<DateTime<Utc>, TimerError> {
+    i64::try_from(timestamp)
+        .ok()
+        .and_then(|timestamp| Utc.timestamp_opt(timestamp, 0).single())
+        .ok_or_else(|| invalid(timestamp))
+}
+
+fn invalid(timestamp: u64) -> TimerError {
+    TimerError::new(&format!("Invalid timestamp: {}.", timestamp))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ClockFormat {
+    #[default]
+    H24,
+    H12,
+}
+
+impl FromStr for ClockFormat {
+    type Err = TimerError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "24" | "24h" => Ok(ClockFormat::H24),
+            "12" | "12h" => Ok(ClockFormat::H12),
+            _ => Err(TimerError::new(&format!(
+                "Unknown clock: {}. Expected 12 or 24.",
+                value
+            ))),
+        }
+    }
+}
+
+impl ClockFormat {
+    pub fn time_format(&self, seconds: bool) -> &'static str {
+        match (self, seconds) {
+            (ClockFormat::H24, true) => "%H:%M:%S",
+            (ClockFormat::H24, false) => "%H:%M",
+            (ClockFormat::H12, true) => "%I:%M:%S %p",
+            (ClockFormat::H12, false) => "%I:%M %p",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DurationFormat {
+    // 01:23:45
+    #[default]
+    Hms,
+    // 01:23
+    Hm,
+    // 1h 23m
+    Short,
+    // 1.38h
+    Decimal,
+}
+
+impl FromStr for DurationFormat {
+    type Err = TimerError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "hms" => Ok(DurationFormat::Hms),
+            "hm" => Ok(DurationFormat::Hm),
+            "short" => Ok(DurationFormat::Short),
+            "decimal" => Ok(DurationFormat::Decimal),
+            _ => Err(TimerError::new(&format!(
+                "Unknown duration format: {}. Expected hms, hm, short or decimal.",
+                value
+            ))),
+        }
+    }
+}
+
+impl DurationFormat {
+    pub fn format(&self, duration_secs: u64) -> String {
+        let hours = duration_secs / 3600;
+        let minutes = (duration_secs % 3600) / 60;
+        let seconds = duration_secs % 60;
+
+        match self {
+            DurationFormat::Hms => format!("{:02}:{:02}:{:02}", hours, minutes, seconds),
+            DurationFormat::Hm => format!("{:02}:{:02}", hours, minutes),
+            DurationFormat::Short if hours > 0 => format!("{}h {}m", hours, minutes),
+            DurationFormat::Short => format!("{}m", minutes),
+            DurationFormat::Decimal => format!("{:.2}h", duration_secs as f64 / 3600.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Formats {
+    pub date_time: String,
+    pub clock: ClockFormat,
+    pub duration: DurationFormat,
+}
+
+impl Default for Formats {
+    fn default() -> Self {
+        Self::new(None, ClockFormat::default(), DurationFormat::default())
+    }
+}
+
+impl Formats {
+    // Explicit date/time pattern wins over the one implied by the clock.
+    pub fn new(date_time: Option<String>, clock: ClockFormat, duration: DurationFormat) -> Self {
+        let date_time = date_time.unwrap_or_else(|| {
+            match clock {
+                ClockFormat::H24 => DEFAULT_DATE_TIME_FORMAT,
+                ClockFormat::H12 => DEFAULT_DATE_TIME_FORMAT_12H,
+            }
+            .to_string()
+        });
+        Self {
+            date_time,
+            clock,
+            duration,
+        }
+    }
+
+    // The date part of the date/time pattern, for places that show whole days, e.g.
+    // `%d.%m.%Y` from `%d.%m.%Y %H:%M`.
+    pub fn date(&self) -> String {
+        let mut date = String::new();
+        let (mut separator, mut time_between) = (String::new(), false);
+        let mut chars = self.date_time.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                separator.push(c);
+                continue;
+            }
+            let mut specifier = String::from(c);
+            while let Some(&next) = chars.peek() {
+                chars.next();
+                specifier.push(next);
+                if next.is_ascii_alphabetic() || next == '%' {
+                    break;
+                }
+            }
+            match specifier.chars().last() {
+                Some('%' | 'n' | 't') => separator.push_str(&specifier),
+                Some(
+                    'Y' | 'C' | 'y' | 'm' | 'b' | 'B' | 'h' | 'd' | 'e' | 'a' | 'A' | 'w' | 'u'
+                    | 'U' | 'W' | 'G' | 'g' | 'V' | 'j' | 'D' | 'x' | 'F' | 'v',
+                ) => {
+                    if !date.is_empty() && !time_between {
+                        date.push_str(&separator);
+                    }
+                    date.push_str(&specifier);
+                    separator.clear();
+                    time_between = false;
+                }
+                Some('c') => {
+                    date.push_str("%a %b %e %Y");
+                    separator.clear();
+                }
+                _ => {
+                    separator.clear();
+                    time_between = true;
+                }
+            }
+        }
+        if date.is_empty() {
+            DEFAULT_DATE_FORMAT.to_string()
+        } else {
+            date
+        }
+    }
+}
+
+// chrono panics while formatting with an invalid pattern, so patterns given by users
+// are checked up front.
+pub fn parse_date_time_format(value: &str) -> Result<String, TimerError> {
+    if StrftimeItems::new(value).any(|item| item == Item::Error) {
+        return Err(TimerError::new(&format!(
+            "Invalid date/time format: {}.",
+            value
+        )));
+    }
+    Ok(value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(date_time: &str) -> String {
+        Formats::new(
+            Some(date_time.to_string()),
+            ClockFormat::H24,
+            DurationFormat::Hms,
+        )
+        .date()
+    }
+
+    #[test]
+    fn date_is_taken_from_date_time_format() {
+        assert_eq!(Formats::default().date(), "%Y-%m-%d");
+        assert_eq!(date("%d.%m.%Y %H:%M"), "%d.%m.%Y");
+        assert_eq!(date("%H:%M, %a %d %b %Y"), "%a %d %b %Y");
+        assert_eq!(date("%-d/%-m/%Y %I:%M %p"), "%-d/%-m/%Y");
+        assert_eq!(date("%H:%M"), "%Y-%m-%d");
+    }
+}
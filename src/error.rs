@@ -1,3 +1,5 @@
+use std::fmt;
+
 #[derive(Debug)]
 pub struct TimerError {
     pub details: String
@@ -7,4 +9,12 @@ impl TimerError {
     pub fn new(msg: &str) -> Self {
         Self{details: msg.to_string()}
     }
-}
\ No newline at end of file
+}
+
+impl fmt::Display for TimerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.details)
+    }
+}
+
+impl std::error::Error for TimerError {}
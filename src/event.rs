@@ -0,0 +1,218 @@
+use std::{
+    io,
+    path::Path,
+    sync::mpsc::{self, Receiver},
+    time::{Duration, Instant},
+};
+
+use crossterm::event::{self, Event, KeyEvent, MouseEvent};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppEvent {
+    Tick,
+    Key(KeyEvent),
+    Mouse(MouseEvent),
+    Resize(u16, u16),
+    DataChanged,
+}
+
+// Where terminal events come from, so tests can do without a terminal.
+pub trait EventSource {
+    fn poll(&mut self, timeout: Duration) -> io::Result<bool>;
+    fn read(&mut self) -> io::Result<Event>;
+}
+
+pub struct TerminalEvents;
+
+impl EventSource for TerminalEvents {
+    fn poll(&mut self, timeout: Duration) -> io::Result<bool> {
+        event::poll(timeout)
+    }
+
+    fn read(&mut self) -> io::Result<Event> {
+        event::read()
+    }
+}
+
+// Terminal events plus a tick every `tick_rate`; waiting for the next one blocks, so the
+// loop sleeps while nothing happens. Changes to the data file are noticed by the next tick.
+pub struct Events<S = TerminalEvents> {
+    source: S,
+    tick_rate: Duration,
+    last_tick: Instant,
+    watcher: Option<(RecommendedWatcher, Receiver<()>)>,
+}
+
+impl Events {
+    pub fn new(tick_rate: Duration) -> Self {
+        Self::with_source(TerminalEvents, tick_rate)
+    }
+}
+
+impl<S: EventSource> Events<S> {
+    pub fn with_source(source: S, tick_rate: Duration) -> Self {
+        Self {
+            source,
+            tick_rate,
+            last_tick: Instant::now(),
+            watcher: None,
+        }
+    }
+
+    // Reports writes to the data file and its journal. The directory is watched, as a file
+    // replaced by a rename would no longer be.
+    pub fn watch(&mut self, data_file: &Path) -> notify::Result<()> {
+        let name = data_file.file_name().unwrap_or_default().to_os_string();
+        let mut journal = name.clone();
+        journal.push(".journal");
+        let names = [name, journal];
+        let (tx, rx) = mpsc::channel();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                let Ok(event) = event else {
+                    return;
+                };
+                if !event.kind.is_access()
+                    && event.paths.iter().any(|path| {
+                        path.file_name()
+                            .is_some_and(|file| names.iter().any(|name| name == file))
+                    })
+                {
+                    let _ = tx.send(());
+                }
+            })?;
+        let directory = match data_file.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        watcher.watch(directory, RecursiveMode::NonRecursive)?;
+        self.watcher = Some((watcher, rx));
+        Ok(())
+    }
+
+    // A burst of writes, like a snapshot and its journal, is reported once.
+    fn data_changed(&self) -> bool {
+        let Some((_, changes)) = &self.watcher else {
+            return false;
+        };
+        let mut changed = false;
+        while changes.try_recv().is_ok() {
+            changed = true;
+        }
+        changed
+    }
+
+    pub fn wait(&mut self) -> io::Result<AppEvent> {
+        loop {
+            if self.data_changed() {
+                return Ok(AppEvent::DataChanged);
+            }
+            let timeout = self.tick_rate.saturating_sub(self.last_tick.elapsed());
+            if !self.source.poll(timeout)? {
+                self.last_tick = Instant::now();
+                return Ok(AppEvent::Tick);
+            }
+            // Focus changes and pastes are not used, so they don't cause a redraw.
+            match self.source.read()? {
+                Event::Key(key_event) => return Ok(AppEvent::Key(key_event)),
+                Event::Mouse(mouse_event) => return Ok(AppEvent::Mouse(mouse_event)),
+                Event::Resize(width, height) => return Ok(AppEvent::Resize(width, height)),
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::VecDeque, thread};
+
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    use super::*;
+
+    const TICK_RATE: Duration = Duration::from_millis(100);
+
+    // Hands out queued events, each after the given delay, and waits out the whole timeout
+    // when nothing is queued, like a terminal nobody types in.
+    #[derive(Default)]
+    struct FakeEvents {
+        queue: VecDeque<(Duration, Event)>,
+        timeouts: Vec<Duration>,
+    }
+
+    impl EventSource for FakeEvents {
+        fn poll(&mut self, timeout: Duration) -> io::Result<bool> {
+            self.timeouts.push(timeout);
+            match self.queue.front() {
+                Some((delay, _)) if *delay <= timeout => {
+                    thread::sleep(*delay);
+                    Ok(true)
+                }
+                _ => {
+                    thread::sleep(timeout);
+                    Ok(false)
+                }
+            }
+        }
+
+        fn read(&mut self) -> io::Result<Event> {
+            Ok(self.queue.pop_front().expect("read without an event").1)
+        }
+    }
+
+    fn events(queue: Vec<(Duration, Event)>) -> Events<FakeEvents> {
+        let source = FakeEvents {
+            queue: queue.into(),
+            ..FakeEvents::default()
+        };
+        Events::with_source(source, TICK_RATE)
+    }
+
+    #[test]
+    fn tick_is_sent_once_the_tick_rate_has_passed() {
+        let mut events = events(Vec::new());
+        let started = Instant::now();
+
+        assert_eq!(events.wait().unwrap(), AppEvent::Tick);
+        assert!(started.elapsed() >= TICK_RATE);
+        assert_eq!(events.wait().unwrap(), AppEvent::Tick);
+        assert!(started.elapsed() >= 2 * TICK_RATE);
+
+        // Each wait blocks for about a whole tick instead of returning right away.
+        let timeouts = &events.source.timeouts;
+        assert_eq!(timeouts.len(), 2);
+        assert!(timeouts.iter().all(|timeout| *timeout > TICK_RATE / 2));
+    }
+
+    #[test]
+    fn poll_timeout_shrinks_by_the_time_already_waited() {
+        let key = KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE);
+        let wait = Duration::from_millis(40);
+        let mut events = events(vec![
+            (wait, Event::Key(key)),
+            (Duration::ZERO, Event::FocusGained),
+        ]);
+
+        assert_eq!(events.wait().unwrap(), AppEvent::Key(key));
+        // Ignored events don't start the tick over either.
+        assert_eq!(events.wait().unwrap(), AppEvent::Tick);
+
+        let timeouts = &events.source.timeouts;
+        assert_eq!(timeouts.len(), 3);
+        assert!(timeouts[0] <= TICK_RATE);
+        assert!(timeouts[1] <= TICK_RATE - wait);
+        assert!(timeouts[2] <= timeouts[1]);
+        assert!(events.source.queue.is_empty());
+    }
+
+    #[test]
+    fn late_wait_ticks_without_blocking() {
+        let mut events = events(Vec::new());
+        thread::sleep(TICK_RATE);
+
+        assert_eq!(events.wait().unwrap(), AppEvent::Tick);
+        assert_eq!(events.source.timeouts, [Duration::ZERO]);
+    }
+}
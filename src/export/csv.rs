@@ -0,0 +1,150 @@
+use std::io::Write;
+
+use crate::{
+    error::TimerError,
+    report::{Report, ReportEntry},
+};
+
+use super::ExportOptions;
+
+const HEADER: [&str; 9] = [
+    "project",
+    "timer_id",
+    "start",
+    "end",
+    "duration",
+    "rounded_duration",
+    "note",
+    "amount",
+    "currency",
+];
+
+pub fn write_csv<W: Write>(
+    writer: W,
+    report: &Report,
+    options: &ExportOptions,
+) -> Result<usize, TimerError> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer
+        .write_record(HEADER)
+        .map_err(|e| TimerError::new(&format!("Failed to write CSV: {}", e)))?;
+
+    let timers = join_timers(report);
+    for entry in &timers {
+        let closed = entry.end_time.is_some();
+        let duration = |duration: u64| {
+            if closed {
+                options.formats.duration.format(duration)
+            } else {
+                String::new()
+            }
+        };
+        let end = entry
+            .end_time
+            .map(|end| options.zone.format(end, &options.formats.date_time))
+            .transpose()?
+            .unwrap_or_default();
+        let amount = entry.amount.as_ref().filter(|_| closed);
+
+        writer
+            .write_record([
+                entry.project.clone(),
+                entry.timer_id.to_string(),
+                options
+                    .zone
+                    .format(entry.start_time, &options.formats.date_time)?,
+                end,
+                duration(entry.duration),
+                duration(entry.rounded_duration),
+                entry.note.clone().unwrap_or_default(),
+                amount
+                    .map(|amount| amount.amount.to_string())
+                    .unwrap_or_default(),
+                amount
+                    .map(|amount| amount.currency.clone())
+                    .unwrap_or_default(),
+            ])
+            .map_err(|e| TimerError::new(&format!("Failed to write CSV: {}", e)))?;
+    }
+
+    writer
+        .flush()
+        .map_err(|e| TimerError::new(&format!("Failed to write CSV: {}", e)))?;
+    Ok(timers.len())
+}
+
+// One row per timer: the parts of timers running over midnight are joined again.
+fn join_timers(report: &Report) -> Vec<ReportEntry> {
+    let mut timers: Vec<ReportEntry> = Vec::new();
+    for entry in report.entries() {
+        match timers
+            .iter_mut()
+            .find(|x| x.project_id == entry.project_id && x.timer_id == entry.timer_id)
+        {
+            Some(timer) => {
+                timer.end_time = entry.end_time;
+                timer.duration += entry.duration;
+                timer.rounded_duration += entry.rounded_duration;
+                if let (Some(total), Some(amount)) = (&mut timer.amount, &entry.amount) {
+                    total.amount.0 += amount.amount.0;
+                }
+            }
+            None => timers.push(entry.clone()),
+        }
+    }
+    timers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        datetime::Zone,
+        export::ExportFilter,
+        model::rounding::Rounding,
+        test_support::{project, timer, NOW},
+    };
+
+    const HOUR: u64 = 3600;
+
+    #[test]
+    fn timers_over_midnight_are_one_row() {
+        // 22:00 to 01:00, and one running since 23:00.
+        let alpha = project(
+            1,
+            "Alpha",
+            vec![
+                timer(1, NOW + 10 * HOUR, Some(NOW + 13 * HOUR)),
+                timer(2, NOW + 11 * HOUR, None),
+            ],
+        );
+        let report = Report::build(
+            &[alpha],
+            &ExportFilter::default(),
+            &Zone::Utc,
+            &Rounding::default(),
+            NOW + 14 * HOUR,
+        )
+        .unwrap();
+        let options = ExportOptions {
+            zone: Zone::Utc,
+            ..ExportOptions::default()
+        };
+
+        let mut out = Vec::new();
+        assert_eq!(write_csv(&mut out, &report, &options).unwrap(), 2);
+        let rows: Vec<String> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .skip(1)
+            .map(str::to_string)
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                "Alpha,1,2024-03-15 22:00:00,2024-03-16 01:00:00,03:00:00,03:00:00,,,",
+                "Alpha,2,2024-03-15 23:00:00,,,,,,",
+            ]
+        );
+    }
+}
@@ -0,0 +1,91 @@
+use std::io::Write;
+
+use crate::error::TimerError;
+
+pub struct Table {
+    pub header: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+    // Rows of totals, shown in bold.
+    pub footer: Vec<Vec<String>>,
+}
+
+pub const HTML_STYLE: &str = "body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+th, td { border: 1px solid #999; padding: 0.3em 0.6em; }
+td:not(:first-child), th:not(:first-child) { text-align: right; }
+tfoot td { font-weight: bold; }
+";
+
+pub fn write<W: Write>(mut writer: W, content: &str) -> Result<(), TimerError> {
+    writer
+        .write_all(content.as_bytes())
+        .and_then(|_| writer.flush())
+        .map_err(|e| TimerError::new(&format!("Failed to write document: {}", e)))
+}
+
+pub fn markdown_table(table: &Table) -> String {
+    let row = |cells: &[String]| {
+        let cells: Vec<String> = cells.iter().map(|cell| escape_markdown(cell)).collect();
+        format!("| {} |\n", cells.join(" | "))
+    };
+    let bold = |cells: &[String]| -> Vec<String> {
+        cells
+            .iter()
+            .map(|cell| {
+                if cell.is_empty() {
+                    String::new()
+                } else {
+                    format!("**{}**", cell)
+                }
+            })
+            .collect()
+    };
+
+    let mut out = row(&table.header);
+    let alignment: Vec<&str> = (0..table.header.len())
+        .map(|i| if i == 0 { "---" } else { "---:" })
+        .collect();
+    out.push_str(&format!("| {} |\n", alignment.join(" | ")));
+    for cells in &table.rows {
+        out.push_str(&row(cells));
+    }
+    for cells in &table.footer {
+        out.push_str(&row(&bold(cells)));
+    }
+    out
+}
+
+pub fn html_table(table: &Table) -> String {
+    let row = |cells: &[String], tag: &str| {
+        let cells: String = cells
+            .iter()
+            .map(|cell| format!("<{tag}>{}</{tag}>", escape_html(cell)))
+            .collect();
+        format!("<tr>{}</tr>\n", cells)
+    };
+
+    let mut out = String::from("<table>\n<thead>\n");
+    out.push_str(&row(&table.header, "th"));
+    out.push_str("</thead>\n<tbody>\n");
+    for cells in &table.rows {
+        out.push_str(&row(cells, "td"));
+    }
+    out.push_str("</tbody>\n<tfoot>\n");
+    for cells in &table.footer {
+        out.push_str(&row(cells, "td"));
+    }
+    out.push_str("</tfoot>\n</table>\n");
+    out
+}
+
+pub fn escape_markdown(value: &str) -> String {
+    value.replace('|', "\\|").replace('\n', " ")
+}
+
+pub fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,163 @@
+use std::io::Write;
+
+use crate::{datetime::to_utc, error::TimerError, model::project::Project};
+
+use super::{ExportFilter, ExportOptions};
+
+const ICS_DATE_TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+const MAX_LINE_LENGTH: usize = 75;
+
+pub fn write_ics<W: Write>(
+    mut writer: W,
+    projects: &[Project],
+    filter: &ExportFilter,
+    options: &ExportOptions,
+    now: u64,
+) -> Result<usize, TimerError> {
+    let stamp = format_utc(now)?;
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//timers-rs//timers-rs//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+
+    let mut count = 0;
+    for (project, timer) in filter.apply(projects, &options.zone)? {
+        let Some(end_time) = timer.end_time else {
+            continue;
+        };
+
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}-{}@timers-rs", project.id, timer.id));
+        lines.push(format!("DTSTAMP:{}", stamp));
+        lines.push(format!("DTSTART:{}", format_utc(timer.start_time)?));
+        lines.push(format!("DTEND:{}", format_utc(end_time)?));
+        lines.push(format!("SUMMARY:{}", escape_text(&project.name)));
+        if let Some(note) = &timer.note {
+            lines.push(format!("DESCRIPTION:{}", escape_text(note)));
+        }
+        lines.push("END:VEVENT".to_string());
+        count += 1;
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    for line in lines {
+        writer
+            .write_all(fold_line(&line).as_bytes())
+            .map_err(|e| TimerError::new(&format!("Failed to write iCalendar: {}", e)))?;
+    }
+    writer
+        .flush()
+        .map_err(|e| TimerError::new(&format!("Failed to write iCalendar: {}", e)))?;
+    Ok(count)
+}
+
+fn format_utc(timestamp: u64) -> Result<String, TimerError> {
+    Ok(to_utc(timestamp)?.format(ICS_DATE_TIME_FORMAT).to_string())
+}
+
+fn escape_text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+// RFC 5545 limits content lines to 75 octets, continuation lines start with a space.
+fn fold_line(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > MAX_LINE_LENGTH {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        datetime::Zone,
+        model::timer::Timer,
+        test_support::{project, timer, NOW},
+    };
+
+    #[test]
+    fn closed_timers_become_events() {
+        let note = format!("Review, part 1; see C:\\notes\n{}", "x".repeat(80));
+        let project = project(
+            1,
+            "Alpha, Beta; Gamma",
+            vec![
+                Timer {
+                    note: Some(note),
+                    ..timer(1, NOW, Some(NOW + 3600))
+                },
+                timer(2, NOW + 7200, None),
+            ],
+        );
+        let options = ExportOptions {
+            zone: Zone::Utc,
+            ..ExportOptions::default()
+        };
+
+        let mut out = Vec::new();
+        let count = write_ics(
+            &mut out,
+            &[project],
+            &ExportFilter::default(),
+            &options,
+            NOW,
+        )
+        .unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert_eq!(count, 1);
+        assert!(out
+            .lines()
+            .all(|line| line.trim_end_matches('\r').len() <= MAX_LINE_LENGTH));
+        let unfolded = out.replace("\r\n ", "");
+        let lines: Vec<&str> = unfolded.split("\r\n").collect();
+        assert_eq!(
+            lines.iter().filter(|line| **line == "BEGIN:VEVENT").count(),
+            1
+        );
+        for expected in [
+            "UID:1-1@timers-rs",
+            "DTSTAMP:20240315T120000Z",
+            "DTSTART:20240315T120000Z",
+            "DTEND:20240315T130000Z",
+            "SUMMARY:Alpha\\, Beta\\; Gamma",
+        ] {
+            assert!(lines.contains(&expected), "{}", expected);
+        }
+        let description = format!(
+            "DESCRIPTION:Review\\, part 1\\; see C:\\\\notes\\n{}",
+            "x".repeat(80)
+        );
+        assert!(lines.contains(&description.as_str()));
+    }
+
+    #[test]
+    fn out_of_range_timestamp_is_an_error() {
+        let project = project(1, "Alpha", vec![timer(1, u64::MAX, Some(u64::MAX))]);
+        let result = write_ics(
+            Vec::new(),
+            &[project],
+            &ExportFilter::default(),
+            &ExportOptions::default(),
+            0,
+        );
+
+        assert!(result.unwrap_err().details.contains("Invalid timestamp"));
+    }
+}
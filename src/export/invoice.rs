@@ -0,0 +1,321 @@
+use std::io::Write;
+
+use chrono::NaiveDate;
+
+use crate::{
+    error::TimerError,
+    model::billing::{Cents, Money},
+    report::Report,
+};
+
+use super::document::{escape_html, html_table, markdown_table, write, Table, HTML_STYLE};
+
+#[derive(Debug, Clone)]
+pub struct InvoiceOptions {
+    pub number: String,
+    pub issue_date: NaiveDate,
+    pub due_date: Option<NaiveDate>,
+    // strftime pattern for the dates above and the period.
+    pub date_format: String,
+    pub sender: Vec<String>,
+    pub client: Vec<String>,
+}
+
+struct InvoiceLine {
+    project_id: u64,
+    description: String,
+    duration: u64,
+    hourly_rate: Cents,
+    amount: Money,
+}
+
+struct Invoice<'a> {
+    options: &'a InvoiceOptions,
+    period: String,
+    table: Table,
+}
+
+impl<'a> Invoice<'a> {
+    fn new(report: &Report, options: &'a InvoiceOptions) -> Self {
+        let mut lines: Vec<InvoiceLine> = Vec::new();
+        for entry in report.entries() {
+            let (Some(_), Some(hourly_rate), Some(amount)) =
+                (entry.end_time, entry.billing.hourly_rate, &entry.amount)
+            else {
+                continue;
+            };
+            match lines.iter_mut().find(|line| {
+                line.project_id == entry.project_id
+                    && line.hourly_rate == hourly_rate
+                    && line.amount.currency == amount.currency
+            }) {
+                Some(line) => {
+                    line.duration += entry.rounded_duration;
+                    line.amount.amount.0 += amount.amount.0;
+                }
+                None => lines.push(InvoiceLine {
+                    project_id: entry.project_id,
+                    description: entry.project.clone(),
+                    duration: entry.rounded_duration,
+                    hourly_rate,
+                    amount: amount.clone(),
+                }),
+            }
+        }
+
+        // Hours and amounts in different currencies don't add up, so each gets a total.
+        let mut totals: Vec<(u64, Money)> = Vec::new();
+        for line in &lines {
+            match totals
+                .iter_mut()
+                .find(|(_, total)| total.currency == line.amount.currency)
+            {
+                Some((duration, total)) => {
+                    *duration += line.duration;
+                    total.amount.0 += line.amount.amount.0;
+                }
+                None => totals.push((line.duration, line.amount.clone())),
+            }
+        }
+        if totals.is_empty() {
+            totals.push((
+                0,
+                Money {
+                    amount: Cents(0),
+                    currency: String::new(),
+                },
+            ));
+        }
+
+        let table = Table {
+            header: vec![
+                "Description".to_string(),
+                "Hours".to_string(),
+                "Rate".to_string(),
+                "Amount".to_string(),
+            ],
+            rows: lines
+                .iter()
+                .map(|line| {
+                    vec![
+                        line.description.clone(),
+                        format_hours(line.duration),
+                        Money {
+                            amount: line.hourly_rate,
+                            currency: line.amount.currency.clone(),
+                        }
+                        .format(),
+                        line.amount.format(),
+                    ]
+                })
+                .collect(),
+            footer: totals
+                .iter()
+                .map(|(duration, total)| {
+                    vec![
+                        "Total".to_string(),
+                        format_hours(*duration),
+                        String::new(),
+                        total.format(),
+                    ]
+                })
+                .collect(),
+        };
+
+        let period = match (report.days.first(), report.days.last()) {
+            (Some(first), Some(last)) => format!(
+                "{} – {}",
+                first.date.format(&options.date_format),
+                last.date.format(&options.date_format)
+            ),
+            _ => "No billable entries".to_string(),
+        };
+
+        Self {
+            options,
+            period,
+            table,
+        }
+    }
+
+    fn details(&self) -> Vec<String> {
+        let mut details = vec![
+            format!("Invoice number: {}", self.options.number),
+            format!(
+                "Issue date: {}",
+                self.options.issue_date.format(&self.options.date_format)
+            ),
+        ];
+        if let Some(due_date) = self.options.due_date {
+            details.push(format!(
+                "Due date: {}",
+                due_date.format(&self.options.date_format)
+            ));
+        }
+        details.push(format!("Period: {}", self.period));
+        details
+    }
+
+    fn to_markdown(&self) -> String {
+        let block = |lines: &[String]| lines.join("  \n");
+
+        let mut out = format!("# Invoice {}\n\n", self.options.number);
+        out.push_str(&format!("{}\n\n", block(&self.details())));
+        if !self.options.sender.is_empty() {
+            out.push_str(&format!("**From**  \n{}\n\n", block(&self.options.sender)));
+        }
+        if !self.options.client.is_empty() {
+            out.push_str(&format!(
+                "**Bill to**  \n{}\n\n",
+                block(&self.options.client)
+            ));
+        }
+        out.push_str(&markdown_table(&self.table));
+        out
+    }
+
+    fn to_html(&self) -> String {
+        let block = |lines: &[String]| {
+            lines
+                .iter()
+                .map(|line| escape_html(line))
+                .collect::<Vec<_>>()
+                .join("<br>\n")
+        };
+
+        let mut out = String::new();
+        out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+        out.push_str(&format!(
+            "<title>Invoice {}</title>\n<style>\n",
+            escape_html(&self.options.number)
+        ));
+        out.push_str(HTML_STYLE);
+        out.push_str("</style>\n</head>\n<body>\n");
+        out.push_str(&format!(
+            "<h1>Invoice {}</h1>\n",
+            escape_html(&self.options.number)
+        ));
+        out.push_str(&format!("<p>{}</p>\n", block(&self.details())));
+        if !self.options.sender.is_empty() {
+            out.push_str(&format!(
+                "<h2>From</h2>\n<p>{}</p>\n",
+                block(&self.options.sender)
+            ));
+        }
+        if !self.options.client.is_empty() {
+            out.push_str(&format!(
+                "<h2>Bill to</h2>\n<p>{}</p>\n",
+                block(&self.options.client)
+            ));
+        }
+        out.push_str(&html_table(&self.table));
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+}
+
+pub fn write_markdown<W: Write>(
+    writer: W,
+    report: &Report,
+    options: &InvoiceOptions,
+) -> Result<(), TimerError> {
+    write(writer, &Invoice::new(report, options).to_markdown())
+}
+
+pub fn write_html<W: Write>(
+    writer: W,
+    report: &Report,
+    options: &InvoiceOptions,
+) -> Result<(), TimerError> {
+    write(writer, &Invoice::new(report, options).to_html())
+}
+
+fn format_hours(duration: u64) -> String {
+    format!("{:.2}", duration as f64 / 3600.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        datetime::Zone,
+        export::ExportFilter,
+        model::{billing::Billing, rounding::Rounding},
+        test_support::{project, timer, NOW},
+    };
+
+    const HOUR: u64 = 3600;
+
+    #[test]
+    fn running_timers_are_not_billed() {
+        // Closed 10:00 to 12:00, running since 22:00 over midnight.
+        let mut alpha = project(
+            1,
+            "Alpha",
+            vec![
+                timer(1, NOW - 2 * HOUR, Some(NOW)),
+                timer(2, NOW + 10 * HOUR, None),
+            ],
+        );
+        alpha.billing = Billing {
+            hourly_rate: Some(Cents(10000)),
+            currency: Some("EUR".to_string()),
+            billable: None,
+        };
+        let report = Report::build(
+            &[alpha],
+            &ExportFilter::default(),
+            &Zone::Utc,
+            &Rounding::default(),
+            NOW + 14 * HOUR,
+        )
+        .unwrap();
+        let options = InvoiceOptions {
+            number: "1".to_string(),
+            issue_date: NaiveDate::from_ymd_opt(2024, 3, 16).unwrap(),
+            due_date: None,
+            date_format: "%d.%m.%Y".to_string(),
+            sender: Vec::new(),
+            client: Vec::new(),
+        };
+
+        let mut out = Vec::new();
+        write_markdown(&mut out, &report, &options).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("| Alpha | 2.00 | 100.00 EUR | 200.00 EUR |"));
+        assert!(out.contains("| **Total** | **2.00** |  | **200.00 EUR** |"));
+        assert!(out.contains("Issue date: 16.03.2024"));
+        assert!(out.contains("Period: 15.03.2024 – 16.03.2024"));
+    }
+
+    #[test]
+    fn projects_with_the_same_name_are_billed_apart() {
+        let alpha = |id, cents| {
+            let mut alpha = project(id, "Alpha", vec![timer(1, NOW, Some(NOW + HOUR))]);
+            alpha.billing.hourly_rate = Some(Cents(cents));
+            alpha
+        };
+        let report = Report::build(
+            &[alpha(1, 5000), alpha(2, 8000)],
+            &ExportFilter::default(),
+            &Zone::Utc,
+            &Rounding::default(),
+            NOW + HOUR,
+        )
+        .unwrap();
+        let options = InvoiceOptions {
+            number: "1".to_string(),
+            issue_date: NaiveDate::from_ymd_opt(2024, 3, 16).unwrap(),
+            due_date: None,
+            date_format: "%Y-%m-%d".to_string(),
+            sender: Vec::new(),
+            client: Vec::new(),
+        };
+
+        let mut out = Vec::new();
+        write_markdown(&mut out, &report, &options).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("| Alpha | 1.00 | 50.00 | 50.00 |"));
+        assert!(out.contains("| Alpha | 1.00 | 80.00 | 80.00 |"));
+    }
+}
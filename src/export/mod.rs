@@ -0,0 +1,84 @@
+pub mod csv;
+mod document;
+pub mod ics;
+pub mod invoice;
+pub mod timesheet;
+
+use chrono::{Days, NaiveDate};
+
+use crate::{
+    datetime::{Formats, Zone},
+    error::TimerError,
+    model::{project::Project, rounding::Rounding, timer::Timer},
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct ExportFilter {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub project: Option<String>,
+}
+
+impl ExportFilter {
+    pub fn matches_project(&self, project: &Project) -> bool {
+        match &self.project {
+            Some(name) => {
+                project.name.eq_ignore_ascii_case(name) || project.id.to_string() == *name
+            }
+            None => true,
+        }
+    }
+
+    pub fn matches_timer(&self, timer: &Timer, zone: &Zone) -> Result<bool, TimerError> {
+        Ok(self.matches_date(zone.date(timer.start_time)?))
+    }
+
+    // Timestamps from the start of `from` up to the end of `to`, the whole time without dates.
+    pub fn time_range(&self, zone: &Zone) -> (u64, u64) {
+        let from = self.from.map_or(0, |date| zone.start_of_day(date));
+        let to = self
+            .to
+            .map_or(u64::MAX, |date| zone.start_of_day(date + Days::new(1)));
+        (from, to)
+    }
+
+    pub fn matches_date(&self, date: NaiveDate) -> bool {
+        self.from.is_none_or(|from| date >= from) && self.to.is_none_or(|to| date <= to)
+    }
+
+    pub fn apply<'a>(
+        &self,
+        projects: &'a [Project],
+        zone: &Zone,
+    ) -> Result<Vec<(&'a Project, &'a Timer)>, TimerError> {
+        let mut matches = Vec::new();
+        for project in projects
+            .iter()
+            .filter(|project| self.matches_project(project))
+        {
+            for timer in &project.timers {
+                if self.matches_timer(timer, zone)? {
+                    matches.push((project, timer));
+                }
+            }
+        }
+        Ok(matches)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    pub zone: Zone,
+    pub formats: Formats,
+    pub rounding: Rounding,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            zone: Zone::Local,
+            formats: Formats::default(),
+            rounding: Rounding::default(),
+        }
+    }
+}
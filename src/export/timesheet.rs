@@ -0,0 +1,371 @@
+use std::io::Write;
+
+use crate::{
+    datetime::DurationFormat, error::TimerError, model::billing::format_money, report::Report,
+};
+
+use super::{
+    document::{
+        escape_html, escape_markdown, html_table, markdown_table, write, Table, HTML_STYLE,
+    },
+    ExportFilter, ExportOptions,
+};
+
+struct Section {
+    title: String,
+    table: Table,
+    entries: Vec<String>,
+}
+
+struct Timesheet {
+    period: String,
+    sections: Vec<Section>,
+    summary: Table,
+}
+
+impl Timesheet {
+    fn new(
+        report: &Report,
+        filter: &ExportFilter,
+        options: &ExportOptions,
+    ) -> Result<Self, TimerError> {
+        let date_format = options.formats.date();
+        let day_format = format!("%a {}", date_format);
+        let day_format = day_format.as_str();
+        let from = filter.from.or(report.days.first().map(|day| day.date));
+        let to = filter.to.or(report.days.last().map(|day| day.date));
+        let period = match (from, to) {
+            (Some(from), Some(to)) => format!(
+                "{} – {}",
+                from.format(&date_format),
+                to.format(&date_format)
+            ),
+            _ => "No entries".to_string(),
+        };
+
+        let time_format = options.formats.clock.time_format(false);
+        let duration = options.formats.duration;
+
+        let mut header = vec!["Day".to_string()];
+        header.extend(report.projects.iter().cloned());
+        header.push("Total".to_string());
+
+        let sections = report
+            .weeks()
+            .iter()
+            .map(|week| {
+                let rows = week
+                    .days
+                    .iter()
+                    .map(|day| {
+                        let mut row = vec![day.date.format(day_format).to_string()];
+                        row.extend(
+                            day.durations
+                                .iter()
+                                .zip(&day.rounded_durations)
+                                .map(|(raw, rounded)| format_cell(*raw, *rounded, duration)),
+                        );
+                        row.push(format_total(day.total(), day.rounded_total(), duration));
+                        row
+                    })
+                    .collect();
+
+                let mut footer = vec!["Week total".to_string()];
+                footer.extend(
+                    week.durations()
+                        .iter()
+                        .zip(week.rounded_durations())
+                        .map(|(raw, rounded)| format_cell(*raw, rounded, duration)),
+                );
+                footer.push(format_total(week.total(), week.rounded_total(), duration));
+
+                let entries = week
+                    .days
+                    .iter()
+                    .flat_map(|day| {
+                        day.entries.iter().map(move |entry| {
+                            let end = entry
+                                .end_time
+                                .map(|end| options.zone.format(end, time_format))
+                                .transpose()?
+                                .unwrap_or("running".to_string());
+                            let amount = entry
+                                .amount
+                                .as_ref()
+                                .map(|amount| format!(", {}", amount.format()))
+                                .unwrap_or_default();
+                            let note = entry
+                                .note
+                                .as_ref()
+                                .map(|note| format!(": {}", note))
+                                .unwrap_or_default();
+                            Ok(format!(
+                                "{}, {}, {} – {} ({}{}){}",
+                                day.date.format(day_format),
+                                entry.project,
+                                options.zone.format(entry.start_time, time_format)?,
+                                end,
+                                format_entry_duration(
+                                    entry.duration,
+                                    entry.rounded_duration,
+                                    duration,
+                                ),
+                                amount,
+                                note
+                            ))
+                        })
+                    })
+                    .collect::<Result<_, TimerError>>()?;
+
+                Ok(Section {
+                    title: format!("Week {}, {}", week.week.week(), week.week.year()),
+                    table: Table {
+                        header: header.clone(),
+                        rows,
+                        footer: vec![footer],
+                    },
+                    entries,
+                })
+            })
+            .collect::<Result<_, TimerError>>()?;
+
+        let summary = Table {
+            header: vec![
+                "Project".to_string(),
+                "Total".to_string(),
+                "Rounded".to_string(),
+                "Amount".to_string(),
+            ],
+            rows: report
+                .projects
+                .iter()
+                .zip(report.durations())
+                .zip(report.rounded_durations())
+                .zip(report.amounts())
+                .map(|(((project, raw), rounded), amounts)| {
+                    vec![
+                        project.clone(),
+                        duration.format(raw),
+                        duration.format(rounded),
+                        format_money(&amounts),
+                    ]
+                })
+                .collect(),
+            footer: vec![vec![
+                "Total".to_string(),
+                duration.format(report.total()),
+                duration.format(report.rounded_total()),
+                format_money(&report.total_amounts()),
+            ]],
+        };
+
+        Ok(Self {
+            period,
+            sections,
+            summary,
+        })
+    }
+
+    fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        out.push_str("# Timesheet\n\n");
+        out.push_str(&format!("Period: {}\n", self.period));
+
+        for section in &self.sections {
+            out.push_str(&format!("\n## {}\n\n", section.title));
+            out.push_str(&markdown_table(&section.table));
+            out.push('\n');
+            for entry in &section.entries {
+                out.push_str(&format!("- {}\n", escape_markdown(entry)));
+            }
+        }
+
+        out.push_str("\n## Summary\n\n");
+        out.push_str(&markdown_table(&self.summary));
+        out
+    }
+
+    fn to_html(&self) -> String {
+        let mut out = String::new();
+        out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+        out.push_str("<title>Timesheet</title>\n<style>\n");
+        out.push_str(HTML_STYLE);
+        out.push_str("</style>\n</head>\n<body>\n");
+        out.push_str("<h1>Timesheet</h1>\n");
+        out.push_str(&format!("<p>Period: {}</p>\n", escape_html(&self.period)));
+
+        for section in &self.sections {
+            out.push_str(&format!("<h2>{}</h2>\n", escape_html(&section.title)));
+            out.push_str(&html_table(&section.table));
+            out.push_str("<ul>\n");
+            for entry in &section.entries {
+                out.push_str(&format!("<li>{}</li>\n", escape_html(entry)));
+            }
+            out.push_str("</ul>\n");
+        }
+
+        out.push_str("<h2>Summary</h2>\n");
+        out.push_str(&html_table(&self.summary));
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+}
+
+pub fn write_markdown<W: Write>(
+    writer: W,
+    report: &Report,
+    filter: &ExportFilter,
+    options: &ExportOptions,
+) -> Result<(), TimerError> {
+    write(
+        writer,
+        &Timesheet::new(report, filter, options)?.to_markdown(),
+    )
+}
+
+pub fn write_html<W: Write>(
+    writer: W,
+    report: &Report,
+    filter: &ExportFilter,
+    options: &ExportOptions,
+) -> Result<(), TimerError> {
+    write(writer, &Timesheet::new(report, filter, options)?.to_html())
+}
+
+fn format_cell(raw: u64, rounded: u64, duration: DurationFormat) -> String {
+    if raw == 0 && rounded == 0 {
+        String::new()
+    } else {
+        format_total(raw, rounded, duration)
+    }
+}
+
+// Rounded duration is the billed one, raw duration is kept next to it when they differ.
+fn format_total(raw: u64, rounded: u64, duration: DurationFormat) -> String {
+    if raw == rounded {
+        duration.format(raw)
+    } else {
+        format!(
+            "{} (raw {})",
+            duration.format(rounded),
+            duration.format(raw)
+        )
+    }
+}
+
+fn format_entry_duration(raw: u64, rounded: u64, duration: DurationFormat) -> String {
+    if raw == rounded {
+        duration.format(raw)
+    } else {
+        format!("{} → {}", duration.format(raw), duration.format(rounded))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        datetime::Zone,
+        model::{rounding::Rounding, timer::Timer},
+        test_support::{project, timer, NOW},
+    };
+
+    const HOUR: u64 = 3600;
+    const DAY: u64 = 24 * HOUR;
+
+    // Alpha on Friday and Monday, Beta on Monday only, so the report spans two weeks.
+    fn report() -> Report {
+        let alpha = project(
+            1,
+            "Alpha",
+            vec![
+                Timer {
+                    note: Some("Review".to_string()),
+                    ..timer(1, NOW, Some(NOW + HOUR))
+                },
+                timer(2, NOW + 3 * DAY, Some(NOW + 3 * DAY + 2 * HOUR)),
+            ],
+        );
+        let beta = project(
+            2,
+            "<Beta & Co>",
+            vec![timer(
+                1,
+                NOW + 3 * DAY + 3 * HOUR,
+                Some(NOW + 3 * DAY + 3 * HOUR + HOUR / 2),
+            )],
+        );
+        Report::build(
+            &[alpha, beta],
+            &ExportFilter::default(),
+            &Zone::Utc,
+            &Rounding::default(),
+            NOW + 4 * DAY,
+        )
+        .unwrap()
+    }
+
+    fn options() -> ExportOptions {
+        ExportOptions {
+            zone: Zone::Utc,
+            ..ExportOptions::default()
+        }
+    }
+
+    #[test]
+    fn markdown_has_a_table_per_week_with_totals_and_notes() {
+        let mut out = Vec::new();
+        write_markdown(&mut out, &report(), &ExportFilter::default(), &options()).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(
+            out,
+            "# Timesheet
+
+Period: 2024-03-15 – 2024-03-18
+
+## Week 11, 2024
+
+| Day | <Beta & Co> | Alpha | Total |
+| --- | ---: | ---: | ---: |
+| Fri 2024-03-15 |  | 01:00:00 | 01:00:00 |
+| **Week total** |  | **01:00:00** | **01:00:00** |
+
+- Fri 2024-03-15, Alpha, 12:00 – 13:00 (01:00:00): Review
+
+## Week 12, 2024
+
+| Day | <Beta & Co> | Alpha | Total |
+| --- | ---: | ---: | ---: |
+| Mon 2024-03-18 | 00:30:00 | 02:00:00 | 02:30:00 |
+| **Week total** | **00:30:00** | **02:00:00** | **02:30:00** |
+
+- Mon 2024-03-18, Alpha, 12:00 – 14:00 (02:00:00)
+- Mon 2024-03-18, <Beta & Co>, 15:00 – 15:30 (00:30:00)
+
+## Summary
+
+| Project | Total | Rounded | Amount |
+| --- | ---: | ---: | ---: |
+| <Beta & Co> | 00:30:00 | 00:30:00 |  |
+| Alpha | 03:00:00 | 03:00:00 |  |
+| **Total** | **03:30:00** | **03:30:00** |  |
+"
+        );
+    }
+
+    #[test]
+    fn html_escapes_project_names() {
+        let mut out = Vec::new();
+        write_html(&mut out, &report(), &ExportFilter::default(), &options()).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(!out.contains("<Beta"));
+        for expected in [
+            "<tr><th>Day</th><th>&lt;Beta &amp; Co&gt;</th><th>Alpha</th><th>Total</th></tr>",
+            "<tr><td>Mon 2024-03-18</td><td>00:30:00</td><td>02:00:00</td><td>02:30:00</td></tr>",
+            "<li>Mon 2024-03-18, &lt;Beta &amp; Co&gt;, 15:00 – 15:30 (00:30:00)</li>",
+            "<tr><td>Total</td><td>03:30:00</td><td>03:30:00</td><td></td></tr>",
+        ] {
+            assert!(out.contains(expected), "{}", expected);
+        }
+    }
+}
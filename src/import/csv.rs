@@ -0,0 +1,204 @@
+use std::io::Read;
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+
+use crate::{datetime::Zone, error::TimerError};
+
+use super::{project_name, DateOrder, ImportedEntry};
+
+const DATE_FORMATS: [&str; 2] = ["%Y-%m-%d", "%d.%m.%Y"];
+const TIME_FORMATS: [&str; 4] = ["%H:%M:%S", "%I:%M:%S %p", "%H:%M", "%I:%M %p"];
+
+// Toggl Track and Clockify name their columns the same, apart from the case.
+const PROJECT: &str = "Project";
+const DESCRIPTION: &str = "Description";
+const START_DATE: &str = "Start date";
+const START_TIME: &str = "Start time";
+const END_DATE: &str = "End date";
+const END_TIME: &str = "End time";
+
+// Reads a Toggl Track detailed export or a Clockify detailed report. Dates written with
+// slashes are read in `order`, or in the order the file's dates show when none is given.
+pub fn read<R: Read>(
+    reader: R,
+    zone: &Zone,
+    order: Option<DateOrder>,
+) -> Result<Vec<ImportedEntry>, TimerError> {
+    let mut reader = csv::Reader::from_reader(reader);
+    let headers = reader
+        .headers()
+        .map_err(|e| TimerError::new(&format!("Failed to read CSV header: {}", e)))?
+        .clone();
+    let index = |name: &str| {
+        headers
+            .iter()
+            .position(|header| header.trim().eq_ignore_ascii_case(name))
+            .ok_or_else(|| TimerError::new(&format!("Missing CSV column: {}", name)))
+    };
+    let project = index(PROJECT)?;
+    let description = index(DESCRIPTION)?;
+    let start_date = index(START_DATE)?;
+    let start_time = index(START_TIME)?;
+    let end_date = index(END_DATE)?;
+    let end_time = index(END_TIME)?;
+
+    let records = reader
+        .records()
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| TimerError::new(&format!("Failed to read CSV row: {}", e)))?;
+    let order = match order {
+        Some(order) => order,
+        None => detect_order(
+            records
+                .iter()
+                .flat_map(|record| [record.get(start_date), record.get(end_date)])
+                .flatten(),
+        )?,
+    };
+
+    let mut entries = Vec::new();
+    for (line, record) in records.iter().enumerate() {
+        let field = |i: usize| record.get(i).unwrap_or("").trim();
+        let row_error = || TimerError::new(&format!("Invalid date or time in row {}", line + 2));
+
+        let start = parse_date_time(field(start_date), field(start_time), order, zone)
+            .ok_or_else(row_error)?;
+        let end = if field(end_date).is_empty() {
+            None
+        } else {
+            Some(
+                parse_date_time(field(end_date), field(end_time), order, zone)
+                    .ok_or_else(row_error)?,
+            )
+        };
+
+        entries.push(ImportedEntry {
+            project: project_name(field(project)),
+            start_time: start,
+            end_time: end,
+            note: Some(field(description).to_string()).filter(|note| !note.is_empty()),
+        });
+    }
+    Ok(entries)
+}
+
+// A day over 12 in the first or the second part of a date tells the order. Files with only
+// dates like 03/04/2024 can't be told apart, so the order has to be given then.
+fn detect_order<'a>(dates: impl Iterator<Item = &'a str>) -> Result<DateOrder, TimerError> {
+    let (mut day_first, mut month_first, mut slashed) = (false, false, false);
+    for date in dates {
+        let parts: Vec<u32> = date
+            .trim()
+            .split('/')
+            .map_while(|part| part.parse().ok())
+            .collect();
+        if let [first, second, _] = parts[..] {
+            slashed = true;
+            day_first |= first > 12;
+            month_first |= second > 12;
+        }
+    }
+    match (day_first, month_first) {
+        (true, true) => Err(TimerError::new(
+            "Dates in the file mix day-first and month-first order.",
+        )),
+        (true, false) => Ok(DateOrder::DayFirst),
+        (false, true) => Ok(DateOrder::MonthFirst),
+        (false, false) if slashed => Err(TimerError::new(
+            "Can't tell whether dates are day-first or month-first, use --date-order to choose.",
+        )),
+        // No dates with slashes, so the order isn't used.
+        (false, false) => Ok(DateOrder::MonthFirst),
+    }
+}
+
+fn parse_date_time(date: &str, time: &str, order: DateOrder, zone: &Zone) -> Option<u64> {
+    let date = DATE_FORMATS
+        .iter()
+        .chain([&order.slashed_format()])
+        .find_map(|format| NaiveDate::parse_from_str(date, format).ok())?;
+    let time = TIME_FORMATS
+        .iter()
+        .find_map(|format| NaiveTime::parse_from_str(time, format).ok())?;
+    zone.timestamp(NaiveDateTime::new(date, time))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::NOW;
+
+    fn read_str(content: &str, order: Option<DateOrder>) -> Result<Vec<ImportedEntry>, TimerError> {
+        read(content.as_bytes(), &Zone::Utc, order)
+    }
+
+    #[test]
+    fn toggl_and_clockify_columns_are_read() {
+        let toggl = "Project,Description,Start date,Start time,End date,End time\n\
+                     Alpha,Review,2024-03-15,12:00:00,2024-03-15,13:30:00\n";
+        let clockify = "Project,Description,Start Date,Start Time,End Date,End Time\n\
+                        Alpha,Review,15.03.2024,12:00 PM,15.03.2024,01:30 PM\n";
+
+        for content in [toggl, clockify] {
+            assert_eq!(
+                read_str(content, None).unwrap(),
+                [ImportedEntry {
+                    project: "Alpha".to_string(),
+                    start_time: NOW,
+                    end_time: Some(NOW + 90 * 60),
+                    note: Some("Review".to_string()),
+                }]
+            );
+        }
+    }
+
+    #[test]
+    fn empty_project_and_end_are_allowed() {
+        let content = "Project,Description,Start date,Start time,End date,End time\n\
+                       ,,2024-03-15,12:00:00,,\n";
+
+        let entry = &read_str(content, None).unwrap()[0];
+        assert_eq!(entry.project, "No project");
+        assert_eq!((entry.end_time, &entry.note), (None, &None));
+    }
+
+    #[test]
+    fn slashed_date_order_is_detected_from_the_file() {
+        let day_first = "Project,Description,Start date,Start time,End date,End time\n\
+                         Alpha,,03/04/2024,12:00,03/04/2024,13:00\n\
+                         Alpha,,15/03/2024,12:00,15/03/2024,13:00\n";
+        let month_first = "Project,Description,Start date,Start time,End date,End time\n\
+                           Alpha,,03/04/2024,12:00,03/04/2024,13:00\n\
+                           Alpha,,03/15/2024,12:00,03/15/2024,13:00\n";
+
+        // 3 April and 4 March 2024.
+        assert_eq!(read_str(day_first, None).unwrap()[0].start_time, 1712145600);
+        assert_eq!(
+            read_str(month_first, None).unwrap()[0].start_time,
+            1709553600
+        );
+    }
+
+    #[test]
+    fn ambiguous_dates_need_an_order() {
+        let content = "Project,Description,Start date,Start time,End date,End time\n\
+                       Alpha,,03/04/2024,12:00,03/04/2024,13:00\n";
+        let mixed = "Project,Description,Start date,Start time,End date,End time\n\
+                     Alpha,,15/03/2024,12:00,03/15/2024,13:00\n";
+
+        assert!(read_str(content, None).is_err());
+        assert!(read_str(mixed, None).is_err());
+        let entries = read_str(content, Some(DateOrder::DayFirst)).unwrap();
+        assert_eq!(entries[0].start_time, 1712145600);
+    }
+
+    #[test]
+    fn invalid_date_reports_row() {
+        let content = "Project,Description,Start date,Start time,End date,End time\n\
+                       Alpha,,2024-03-15,12:00,2024-03-15,13:00\n\
+                       Alpha,,yesterday,12:00,,\n";
+
+        let error = read_str(content, None).unwrap_err();
+        assert_eq!(error.details, "Invalid date or time in row 3");
+    }
+}
@@ -0,0 +1,47 @@
+pub mod csv;
+pub mod timewarrior;
+
+use clap::ValueEnum;
+
+const NO_PROJECT_NAME: &str = "No project";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedEntry {
+    pub project: String,
+    pub start_time: u64,
+    pub end_time: Option<u64>,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    pub created_projects: Vec<String>,
+    pub added: Vec<ImportedEntry>,
+    pub duplicates: usize,
+    // Entries without an end, which would run alongside the timer running here.
+    pub running: usize,
+}
+
+// Order of day and month in dates written like 03/04/2024.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum DateOrder {
+    DayFirst,
+    MonthFirst,
+}
+
+impl DateOrder {
+    fn slashed_format(&self) -> &'static str {
+        match self {
+            DateOrder::DayFirst => "%d/%m/%Y",
+            DateOrder::MonthFirst => "%m/%d/%Y",
+        }
+    }
+}
+
+pub fn project_name(name: &str) -> String {
+    if name.is_empty() {
+        NO_PROJECT_NAME.to_string()
+    } else {
+        name.to_string()
+    }
+}
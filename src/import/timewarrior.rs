@@ -0,0 +1,157 @@
+use std::{fs, path::Path};
+
+use chrono::NaiveDateTime;
+
+use crate::{datetime::Zone, error::TimerError};
+
+use super::{project_name, ImportedEntry};
+
+const TIMEWARRIOR_DATE_TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+// Timewarrior keeps one `YYYY-MM.data` file per month, so both a single file
+// and the whole data directory are accepted.
+pub fn read_path(path: &Path) -> Result<Vec<ImportedEntry>, TimerError> {
+    let read_error =
+        |e: std::io::Error| TimerError::new(&format!("Failed to read {}: {}", path.display(), e));
+
+    if !path.is_dir() {
+        return parse(&fs::read_to_string(path).map_err(read_error)?);
+    }
+
+    let mut files: Vec<_> = fs::read_dir(path)
+        .map_err(read_error)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|file| {
+            file.extension()
+                .is_some_and(|extension| extension == "data")
+        })
+        .collect();
+    files.sort();
+
+    let mut entries = Vec::new();
+    for file in files {
+        entries.extend(parse(&fs::read_to_string(&file).map_err(read_error)?)?);
+    }
+    Ok(entries)
+}
+
+// Parses lines in `inc <start> [- <end>] [# <tags> [# <annotation>]]` form.
+// First tag becomes the project, annotation (or the remaining tags) the note.
+pub fn parse(content: &str) -> Result<Vec<ImportedEntry>, TimerError> {
+    let mut entries = Vec::new();
+    for (line_number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        let Some(line) = line.strip_prefix("inc ") else {
+            continue;
+        };
+        let line_error =
+            || TimerError::new(&format!("Invalid Timewarrior line {}", line_number + 1));
+
+        let mut parts = line.splitn(3, " # ");
+        let interval: Vec<&str> = parts.next().unwrap_or("").split_whitespace().collect();
+        let tags = parse_tags(parts.next().unwrap_or(""));
+        let annotation = parts.next().map(|annotation| unquote(annotation.trim()));
+
+        let (start, end) = match interval.as_slice() {
+            [start] => (parse_timestamp(start).ok_or_else(line_error)?, None),
+            [start, "-", end] => (
+                parse_timestamp(start).ok_or_else(line_error)?,
+                Some(parse_timestamp(end).ok_or_else(line_error)?),
+            ),
+            _ => return Err(line_error()),
+        };
+
+        let note = annotation
+            .or_else(|| Some(tags.iter().skip(1).cloned().collect::<Vec<_>>().join(", ")))
+            .filter(|note| !note.is_empty());
+
+        entries.push(ImportedEntry {
+            project: project_name(tags.first().map(String::as_str).unwrap_or("")),
+            start_time: start,
+            end_time: end,
+            note,
+        });
+    }
+    Ok(entries)
+}
+
+fn parse_timestamp(value: &str) -> Option<u64> {
+    let date_time = NaiveDateTime::parse_from_str(value, TIMEWARRIOR_DATE_TIME_FORMAT).ok()?;
+    Zone::Utc.timestamp(date_time)
+}
+
+fn parse_tags(value: &str) -> Vec<String> {
+    let mut tags = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut escaped = false;
+    for c in value.chars() {
+        match c {
+            _ if escaped => {
+                current.push(c);
+                escaped = false;
+            }
+            '\\' => escaped = true,
+            '"' => quoted = !quoted,
+            ' ' if !quoted => {
+                if !current.is_empty() {
+                    tags.push(std::mem::take(&mut current));
+                }
+            }
+            _ => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tags.push(current);
+    }
+    tags
+}
+
+fn unquote(value: &str) -> String {
+    parse_tags(value).join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::NOW;
+
+    #[test]
+    fn intervals_tags_and_annotations_are_read() {
+        let content = "inc 20240315T120000Z - 20240315T133000Z # Alpha review # \"Code review\"\n\
+                       inc 20240315T140000Z - 20240315T150000Z # \"Big project\" design docs\n\
+                       inc 20240315T160000Z\n\
+                       not an interval\n";
+
+        let entries = parse(content).unwrap();
+        assert_eq!(
+            entries,
+            [
+                ImportedEntry {
+                    project: "Alpha".to_string(),
+                    start_time: NOW,
+                    end_time: Some(NOW + 90 * 60),
+                    note: Some("Code review".to_string()),
+                },
+                ImportedEntry {
+                    project: "Big project".to_string(),
+                    start_time: NOW + 2 * 3600,
+                    end_time: Some(NOW + 3 * 3600),
+                    note: Some("design, docs".to_string()),
+                },
+                ImportedEntry {
+                    project: "No project".to_string(),
+                    start_time: NOW + 4 * 3600,
+                    end_time: None,
+                    note: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_interval_reports_line() {
+        let error = parse("inc 20240315T120000Z\ninc yesterday\n").unwrap_err();
+        assert_eq!(error.details, "Invalid Timewarrior line 2");
+    }
+}
@@ -0,0 +1,433 @@
+use std::{fmt, str::FromStr};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Deserializer};
+
+use crate::{action::Action, error::TimerError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyPress {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyPress {
+    // Shift is already part of the character ('G'), so it is not compared on its own.
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let modifiers = match code {
+            KeyCode::Char(_) => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+        Self { code, modifiers }
+    }
+}
+
+impl From<KeyEvent> for KeyPress {
+    fn from(key_event: KeyEvent) -> Self {
+        Self::new(key_event.code, key_event.modifiers)
+    }
+}
+
+impl FromStr for KeyPress {
+    type Err = TimerError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let error = || TimerError::new(&format!("Invalid key: {}.", value));
+        let (modifier_names, key) = match value.rsplit_once('+') {
+            Some((modifiers, key)) if !key.is_empty() => (modifiers, key),
+            _ => ("", value),
+        };
+
+        let mut modifiers = KeyModifiers::NONE;
+        for modifier in modifier_names.split('+').filter(|name| !name.is_empty()) {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(error()),
+            };
+        }
+
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            // `shift+s` is the `S` key. Other characters depend on the keyboard layout, so
+            // the shifted one has to be written, like `!` for `shift+1`.
+            (Some(c), None) if modifiers.contains(KeyModifiers::SHIFT) => {
+                if !c.is_alphabetic() {
+                    return Err(TimerError::new(&format!(
+                        "Invalid key: {}. Write the shifted character instead.",
+                        value
+                    )));
+                }
+                KeyCode::Char(c.to_uppercase().next().unwrap_or(c))
+            }
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match key.to_lowercase().as_str() {
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "enter" => KeyCode::Enter,
+                "esc" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                "space" => KeyCode::Char(' '),
+                name => name
+                    .strip_prefix('f')
+                    .and_then(|number| number.parse().ok())
+                    .filter(|number| (1..=12).contains(number))
+                    .map(KeyCode::F)
+                    .ok_or_else(error)?,
+            },
+        };
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+impl fmt::Display for KeyPress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "Shift+")?;
+        }
+        match self.code {
+            KeyCode::Up => write!(f, "↑"),
+            KeyCode::Down => write!(f, "↓"),
+            KeyCode::Left => write!(f, "←"),
+            KeyCode::Right => write!(f, "→"),
+            KeyCode::Enter => write!(f, "⏎"),
+            KeyCode::Esc => write!(f, "Esc"),
+            KeyCode::Tab => write!(f, "Tab"),
+            KeyCode::BackTab => write!(f, "Shift+Tab"),
+            KeyCode::Backspace => write!(f, "Backspace"),
+            KeyCode::Delete => write!(f, "Del"),
+            KeyCode::Home => write!(f, "Home"),
+            KeyCode::End => write!(f, "End"),
+            KeyCode::PageUp => write!(f, "PgUp"),
+            KeyCode::PageDown => write!(f, "PgDn"),
+            KeyCode::F(number) => write!(f, "F{}", number),
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            _ => write!(f, "?"),
+        }
+    }
+}
+
+// One or more keys pressed one after another, like `g g`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeySequence(pub Vec<KeyPress>);
+
+impl FromStr for KeySequence {
+    type Err = TimerError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let keys = value
+            .split_whitespace()
+            .map(KeyPress::from_str)
+            .collect::<Result<Vec<_>, _>>()?;
+        if keys.is_empty() {
+            return Err(TimerError::new("Key binding must not be empty."));
+        }
+        Ok(Self(keys))
+    }
+}
+
+impl fmt::Display for KeySequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let keys: Vec<String> = self.0.iter().map(KeyPress::to_string).collect();
+        let plain = self
+            .0
+            .iter()
+            .all(|key| matches!(key.code, KeyCode::Char(_)) && key.modifiers.is_empty());
+        write!(f, "{}", keys.join(if plain { "" } else { " " }))
+    }
+}
+
+// Bindings of one action in the config file, either a single key or a list of keys.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyBindings(pub Vec<KeySequence>);
+
+impl<'de> Deserialize<'de> for KeyBindings {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Value {
+            One(String),
+            Many(Vec<String>),
+        }
+
+        let values = match Value::deserialize(deserializer)? {
+            Value::One(value) => vec![value],
+            Value::Many(values) => values,
+        };
+        values
+            .iter()
+            .map(|value| value.parse())
+            .collect::<Result<Vec<_>, TimerError>>()
+            .map(KeyBindings)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+// Keys given to more than one action in the config file; which one would get them depends on
+// the order of the actions, so that is an error.
+pub fn check_bindings<'a>(
+    bindings: impl IntoIterator<Item = (&'a Action, &'a KeyBindings)>,
+) -> Result<(), TimerError> {
+    let mut seen: Vec<(&KeySequence, Action)> = Vec::new();
+    for (action, keys) in bindings {
+        for keys in &keys.0 {
+            match seen.iter().find(|(seen_keys, _)| *seen_keys == keys) {
+                Some((_, other)) if other != action => {
+                    return Err(TimerError::new(&format!(
+                        "Key {} is bound to both {} and {}.",
+                        keys,
+                        other.description(),
+                        action.description()
+                    )));
+                }
+                Some(_) => {}
+                None => seen.push((keys, *action)),
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeymapPreset {
+    #[default]
+    Default,
+    Vim,
+}
+
+const DEFAULT_BINDINGS: [(&str, Action); 19] = [
+    ("ctrl+q", Action::Quit),
+    ("up", Action::Up),
+    ("down", Action::Down),
+    ("left", Action::Left),
+    ("right", Action::Right),
+    ("home", Action::First),
+    ("end", Action::Last),
+    ("tab", Action::NextPanel),
+    ("enter", Action::Confirm),
+    ("esc", Action::Cancel),
+    ("n", Action::NewProject),
+    ("e", Action::EditProject),
+    ("d", Action::DeleteProject),
+    ("s", Action::StartTimer),
+    ("S", Action::StopTimer),
+    ("x", Action::ExportCsv),
+    ("ctrl+p", Action::CommandPalette),
+    ("?", Action::Help),
+    ("f1", Action::Help),
+];
+
+const VIM_BINDINGS: [(&str, Action); 6] = [
+    ("k", Action::Up),
+    ("j", Action::Down),
+    ("h", Action::Left),
+    ("l", Action::Right),
+    ("g g", Action::First),
+    ("G", Action::Last),
+];
+
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<(KeySequence, Action)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::preset(KeymapPreset::Default)
+    }
+}
+
+impl Keymap {
+    pub fn preset(preset: KeymapPreset) -> Self {
+        let extra: &[(&str, Action)] = match preset {
+            KeymapPreset::Default => &[],
+            KeymapPreset::Vim => &VIM_BINDINGS,
+        };
+        let bindings = DEFAULT_BINDINGS
+            .iter()
+            .chain(extra)
+            .map(|(keys, action)| (keys.parse().expect("Invalid built-in key"), *action))
+            .collect();
+        Self { bindings }
+    }
+
+    // Replaces all bindings of the action; the keys are taken away from other actions.
+    pub fn bind(&mut self, action: Action, bindings: &KeyBindings) {
+        self.bindings
+            .retain(|(keys, bound)| *bound != action && !bindings.0.contains(keys));
+        self.bindings
+            .extend(bindings.0.iter().map(|keys| (keys.clone(), action)));
+    }
+
+    pub fn keys(&self, action: Action) -> Vec<&KeySequence> {
+        self.bindings
+            .iter()
+            .filter(|(_, bound)| *bound == action)
+            .map(|(keys, _)| keys)
+            .collect()
+    }
+
+    pub fn describe(&self, action: Action) -> String {
+        let keys: Vec<String> = self
+            .keys(action)
+            .iter()
+            .map(|keys| keys.to_string())
+            .collect();
+        keys.join("/")
+    }
+
+    pub fn action(&self, key: KeyPress) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(keys, _)| keys.0 == [key])
+            .map(|(_, action)| *action)
+    }
+
+    // Pending keys are kept while they are the start of a longer binding, like the first `g`
+    // of `g g`. A key that breaks such a sequence is looked up on its own.
+    pub fn resolve(&self, pending: &mut Vec<KeyPress>, key: KeyPress) -> Option<Action> {
+        pending.push(key);
+        if let Some((_, action)) = self.bindings.iter().find(|(keys, _)| keys.0 == *pending) {
+            pending.clear();
+            return Some(*action);
+        }
+        if self
+            .bindings
+            .iter()
+            .any(|(keys, _)| keys.0.starts_with(pending))
+        {
+            return None;
+        }
+
+        let retry = pending.len() > 1;
+        pending.clear();
+        if retry {
+            self.resolve(pending, key)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(value: &str) -> KeyPress {
+        value.parse().unwrap()
+    }
+
+    fn resolve_all(keymap: &Keymap, keys: &str) -> Vec<Option<Action>> {
+        let mut pending = Vec::new();
+        keys.split_whitespace()
+            .map(|value| keymap.resolve(&mut pending, key(value)))
+            .collect()
+    }
+
+    #[test]
+    fn keys_are_parsed() {
+        assert_eq!(
+            key("ctrl+q"),
+            KeyPress::new(KeyCode::Char('q'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(
+            key("Alt+Enter"),
+            KeyPress::new(KeyCode::Enter, KeyModifiers::ALT)
+        );
+        assert_eq!(
+            key("space"),
+            KeyPress::new(KeyCode::Char(' '), KeyModifiers::NONE)
+        );
+        assert_eq!(
+            key("f12"),
+            KeyPress::new(KeyCode::F(12), KeyModifiers::NONE)
+        );
+        assert_eq!(
+            key("+"),
+            KeyPress::new(KeyCode::Char('+'), KeyModifiers::NONE)
+        );
+        assert_eq!(key("ctrl+q").to_string(), "Ctrl+q");
+        for invalid in ["f13", "hyper+x", "ctrl+", "enterr"] {
+            assert!(invalid.parse::<KeyPress>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn shift_with_a_letter_is_the_capital_letter() {
+        let shifted = KeyPress::from(KeyEvent::new(KeyCode::Char('S'), KeyModifiers::SHIFT));
+        assert_eq!(key("shift+s"), key("S"));
+        assert_eq!(key("S"), shifted);
+        assert_ne!(key("shift+s"), key("s"));
+
+        let error = "shift+1".parse::<KeyPress>().unwrap_err();
+        assert!(error.details.contains("shifted character"));
+    }
+
+    #[test]
+    fn sequences_wait_for_their_next_key() {
+        let keymap = Keymap::preset(KeymapPreset::Vim);
+
+        assert_eq!(resolve_all(&keymap, "g g"), [None, Some(Action::First)]);
+        // A key that breaks the sequence counts on its own.
+        assert_eq!(resolve_all(&keymap, "g j"), [None, Some(Action::Down)]);
+        assert_eq!(resolve_all(&keymap, "g x"), [None, Some(Action::ExportCsv)]);
+        assert_eq!(keymap.keys(Action::First).len(), 2);
+    }
+
+    #[test]
+    fn vim_preset_adds_to_the_default_keys() {
+        let default = Keymap::default();
+        let vim = Keymap::preset(KeymapPreset::Vim);
+
+        assert_eq!(default.action(key("j")), None);
+        assert_eq!(vim.action(key("j")), Some(Action::Down));
+        assert_eq!(vim.action(key("G")), Some(Action::Last));
+        assert_eq!(vim.action(key("down")), Some(Action::Down));
+        assert_eq!(vim.describe(Action::First), "Home/gg");
+    }
+
+    #[test]
+    fn rebinding_takes_keys_from_other_actions() {
+        let mut keymap = Keymap::default();
+        keymap.bind(Action::Quit, &KeyBindings(vec!["s".parse().unwrap()]));
+
+        assert_eq!(keymap.action(key("s")), Some(Action::Quit));
+        assert_eq!(keymap.action(key("ctrl+q")), None);
+        assert!(keymap.keys(Action::StartTimer).is_empty());
+    }
+
+    #[test]
+    fn keys_bound_to_two_actions_are_refused() {
+        let bindings =
+            |keys: &[&str]| KeyBindings(keys.iter().map(|keys| keys.parse().unwrap()).collect());
+        let start = bindings(&["x", "shift+s"]);
+        let stop = bindings(&["S"]);
+
+        let error = check_bindings([(&Action::StartTimer, &start), (&Action::StopTimer, &stop)])
+            .unwrap_err();
+        assert_eq!(
+            error.details,
+            "Key S is bound to both Start timer on selected project and Stop running timer."
+        );
+        let twice = bindings(&["x", "x"]);
+        assert!(check_bindings([(&Action::StartTimer, &twice)]).is_ok());
+    }
+}
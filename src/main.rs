@@ -1,15 +1,70 @@
-use std::io::Result;
+use std::{io::Result, path::Path, rc::Rc};
 
+use clap::Parser;
+
+use crate::{
+    clock::SystemClock, config::Config, error::TimerError, repository::Repository,
+    storage::lock::InstanceLock,
+};
+
+pub mod action;
 pub mod app;
+pub mod cli;
+pub mod clock;
+pub mod config;
 pub mod crossterm;
+pub mod datetime;
+pub mod event;
+pub mod export;
+pub mod import;
+pub mod keymap;
+pub mod palette;
 pub mod ui;
 pub mod model;
+pub mod report;
+pub mod theme;
 pub mod repository;
+pub mod storage;
 pub mod error;
+#[cfg(test)]
+mod test_support;
 
 fn main() -> Result<()> {
     env_logger::init();
-    let _ = crossterm::run();
+    let cli = cli::Cli::parse();
+    let config = match cli.config().and_then(|config| {
+        config.create_data_dir()?;
+        Ok(config)
+    }) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+
+    match cli.command {
+        Some(command) => {
+            if let Err(err) = cli::execute(command, &config) {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
+        None => {
+            if let Err(err) = run_tui(&config) {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    Ok(())
+}
 
+// Only one TUI at a time works on a data file.
+fn run_tui(config: &Config) -> std::result::Result<(), TimerError> {
+    let _instance = InstanceLock::acquire(Path::new(&config.data_file))?;
+    let repository = Repository::open(&config.data_file, config.storage, Rc::new(SystemClock))?;
+    let _ = crossterm::run(config, repository);
     Ok(())
 }
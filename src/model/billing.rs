@@ -0,0 +1,196 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::error::TimerError;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct Billing {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hourly_rate: Option<Cents>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub billable: Option<bool>,
+}
+
+// Hundredths of the currency unit, so amounts add up exactly. Data files keep them as
+// decimal numbers, e.g. `80.5`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Cents(pub i64);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Money {
+    pub amount: Cents,
+    pub currency: String,
+}
+
+impl Billing {
+    pub fn is_empty(&self) -> bool {
+        *self == Billing::default()
+    }
+
+    // Values set on `self` (e.g. a timer) take precedence over the ones from `fallback` (its project).
+    pub fn or(&self, fallback: &Billing) -> Billing {
+        Billing {
+            hourly_rate: self.hourly_rate.or(fallback.hourly_rate),
+            currency: self.currency.clone().or(fallback.currency.clone()),
+            billable: self.billable.or(fallback.billable),
+        }
+    }
+
+    pub fn is_billable(&self) -> bool {
+        self.billable.unwrap_or(self.hourly_rate.is_some())
+    }
+
+    // Rounded to the nearest cent, halves up.
+    pub fn amount(&self, duration_secs: u64) -> Option<Money> {
+        if !self.is_billable() {
+            return None;
+        }
+        self.hourly_rate.map(|rate| Money {
+            amount: Cents(
+                ((rate.0 as i128 * duration_secs as i128 + 1800).div_euclid(3600)) as i64,
+            ),
+            currency: self.currency.clone().unwrap_or_default(),
+        })
+    }
+}
+
+impl fmt::Display for Cents {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let cents = self.0.unsigned_abs();
+        write!(f, "{}{}.{:02}", sign, cents / 100, cents % 100)
+    }
+}
+
+// Read exactly, without going through floating point, e.g. `60`, `60.5` or `60.05`.
+impl FromStr for Cents {
+    type Err = TimerError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || TimerError::new(&format!("Invalid amount: {}.", value));
+        let (units, fraction) = value.trim().split_once('.').unwrap_or((value.trim(), ""));
+        if units.is_empty()
+            || fraction.len() > 2
+            || !units
+                .chars()
+                .chain(fraction.chars())
+                .all(|c| c.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+        let units: i64 = units.parse().map_err(|_| invalid())?;
+        let fraction: i64 = format!("{:0<2}", fraction).parse().map_err(|_| invalid())?;
+        units
+            .checked_mul(100)
+            .and_then(|cents| cents.checked_add(fraction))
+            .map(Cents)
+            .ok_or_else(invalid)
+    }
+}
+
+impl Serialize for Cents {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.0 as f64 / 100.0)
+    }
+}
+
+// Rates can't be negative, like the ones given on the command line.
+impl<'de> Deserialize<'de> for Cents {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let amount = f64::deserialize(deserializer)?;
+        if amount < 0.0 {
+            return Err(serde::de::Error::custom(format!(
+                "invalid amount: {}",
+                amount
+            )));
+        }
+        Ok(Cents((amount * 100.0).round() as i64))
+    }
+}
+
+impl Money {
+    pub fn format(&self) -> String {
+        format!("{} {}", self.amount, self.currency)
+            .trim_end()
+            .to_string()
+    }
+}
+
+pub fn add_money(totals: &mut Vec<Money>, money: &Money) {
+    match totals.iter_mut().find(|x| x.currency == money.currency) {
+        Some(total) => total.amount.0 += money.amount.0,
+        None => totals.push(money.clone()),
+    }
+}
+
+pub fn format_money(totals: &[Money]) -> String {
+    totals
+        .iter()
+        .map(Money::format)
+        .collect::<Vec<_>>()
+        .join(" + ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cents_are_parsed_exactly() {
+        assert_eq!("60".parse::<Cents>().unwrap(), Cents(6000));
+        assert_eq!("60.5".parse::<Cents>().unwrap(), Cents(6050));
+        assert_eq!("0.07".parse::<Cents>().unwrap(), Cents(7));
+        for invalid in ["", ".5", "60.555", "-1", "1e3", "sixty"] {
+            assert!(invalid.parse::<Cents>().is_err(), "{}", invalid);
+        }
+        assert_eq!(Cents(6005).to_string(), "60.05");
+        assert_eq!(Cents(-5).to_string(), "-0.05");
+    }
+
+    #[test]
+    fn cents_are_kept_as_decimal_numbers() {
+        let billing: Billing = serde_json::from_str(r#"{"hourly_rate":80.1}"#).unwrap();
+        assert_eq!(billing.hourly_rate, Some(Cents(8010)));
+        assert_eq!(
+            serde_json::to_string(&billing).unwrap(),
+            r#"{"hourly_rate":80.1}"#
+        );
+        assert!(serde_json::from_str::<Billing>(r#"{"hourly_rate":-80}"#).is_err());
+    }
+
+    #[test]
+    fn amount_is_rounded_to_nearest_cent() {
+        let billing = Billing {
+            hourly_rate: Some(Cents(10)),
+            ..Billing::default()
+        };
+        // 0.10 an hour: 30 minutes are 5 cents, 27 minutes 4.5 cents, 26 minutes 4.33 cents.
+        let cents = |minutes: u64| billing.amount(minutes * 60).unwrap().amount;
+        assert_eq!(
+            (cents(30), cents(27), cents(26)),
+            (Cents(5), Cents(5), Cents(4))
+        );
+
+        let not_billable = Billing {
+            billable: Some(false),
+            ..billing
+        };
+        assert_eq!(not_billable.amount(3600), None);
+    }
+
+    #[test]
+    fn money_is_added_per_currency() {
+        let money = |cents, currency: &str| Money {
+            amount: Cents(cents),
+            currency: currency.to_string(),
+        };
+        let mut totals = Vec::new();
+        for amount in [money(10, "EUR"), money(20, "USD"), money(5, "EUR")] {
+            add_money(&mut totals, &amount);
+        }
+        assert_eq!(totals, [money(15, "EUR"), money(20, "USD")]);
+    }
+}
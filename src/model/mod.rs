@@ -1,2 +1,4 @@
+pub mod billing;
+pub mod rounding;
 pub mod timer;
-pub mod project;
\ No newline at end of file
+pub mod project;
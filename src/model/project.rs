@@ -1,15 +1,33 @@
 use serde::{Deserialize, Serialize};
 
-use super::timer::Timer;
+use super::{billing::Billing, rounding::Rounding, timer::Timer};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Project {
     pub id: u64,
     pub name: String,
     pub timers: Vec<Timer>,
+    #[serde(default, skip_serializing_if = "Billing::is_empty")]
+    pub billing: Billing,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rounding: Option<Rounding>,
 }
 
 impl Project {
+    pub fn new(id: u64, name: String) -> Self {
+        Self {
+            id,
+            name,
+            timers: Vec::new(),
+            billing: Billing::default(),
+            rounding: None,
+        }
+    }
+
+    pub fn get_billing(&self, timer: &Timer) -> Billing {
+        timer.billing.or(&self.billing)
+    }
+
     pub fn get_timers(&self) -> Vec<&Timer> {
         self.timers.iter().collect()
     }
@@ -0,0 +1,140 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::TimerError;
+
+pub const DEFAULT_ROUNDING_INCREMENT: u32 = 15;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RoundingMode {
+    #[default]
+    None,
+    Nearest,
+    Up,
+    Down,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RoundingScope {
+    #[default]
+    Entry,
+    Day,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Rounding {
+    pub mode: RoundingMode,
+    pub increment: u32,
+    pub scope: RoundingScope,
+}
+
+impl Default for Rounding {
+    fn default() -> Self {
+        Self {
+            mode: RoundingMode::None,
+            increment: DEFAULT_ROUNDING_INCREMENT,
+            scope: RoundingScope::Entry,
+        }
+    }
+}
+
+impl Rounding {
+    pub fn is_none(&self) -> bool {
+        self.mode == RoundingMode::None || self.increment == 0
+    }
+
+    pub fn round(&self, duration_secs: u64) -> u64 {
+        if self.is_none() {
+            return duration_secs;
+        }
+        let increment = self.increment as u64 * 60;
+        match self.mode {
+            RoundingMode::None => duration_secs,
+            RoundingMode::Nearest => (duration_secs + increment / 2) / increment * increment,
+            RoundingMode::Up => duration_secs.div_ceil(increment) * increment,
+            RoundingMode::Down => duration_secs / increment * increment,
+        }
+    }
+}
+
+impl FromStr for RoundingMode {
+    type Err = TimerError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "none" => Ok(RoundingMode::None),
+            "nearest" => Ok(RoundingMode::Nearest),
+            "up" => Ok(RoundingMode::Up),
+            "down" => Ok(RoundingMode::Down),
+            _ => Err(TimerError::new(&format!(
+                "Unknown rounding mode: {}. Expected none, nearest, up or down.",
+                value
+            ))),
+        }
+    }
+}
+
+impl FromStr for RoundingScope {
+    type Err = TimerError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "entry" => Ok(RoundingScope::Entry),
+            "day" => Ok(RoundingScope::Day),
+            _ => Err(TimerError::new(&format!(
+                "Unknown rounding scope: {}. Expected entry or day.",
+                value
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rounding(mode: RoundingMode, increment: u32) -> Rounding {
+        Rounding {
+            mode,
+            increment,
+            ..Rounding::default()
+        }
+    }
+
+    // Rounded 7, 22, 30 and 38 minutes.
+    fn rounded(rounding: Rounding) -> Vec<u64> {
+        [7, 22, 30, 38]
+            .iter()
+            .map(|minutes| rounding.round(minutes * 60) / 60)
+            .collect()
+    }
+
+    #[test]
+    fn each_mode_rounds_to_increment() {
+        assert_eq!(rounded(rounding(RoundingMode::None, 15)), [7, 22, 30, 38]);
+        assert_eq!(
+            rounded(rounding(RoundingMode::Nearest, 15)),
+            [0, 15, 30, 45]
+        );
+        assert_eq!(rounded(rounding(RoundingMode::Up, 15)), [15, 30, 30, 45]);
+        assert_eq!(rounded(rounding(RoundingMode::Down, 15)), [0, 15, 30, 30]);
+    }
+
+    #[test]
+    fn nearest_rounds_halves_up() {
+        let rounding = rounding(RoundingMode::Nearest, 15);
+        assert_eq!(rounding.round(7 * 60 + 29), 0);
+        assert_eq!(rounding.round(7 * 60 + 30), 15 * 60);
+    }
+
+    #[test]
+    fn zero_increment_keeps_duration() {
+        let rounding = rounding(RoundingMode::Up, 0);
+        assert!(rounding.is_none());
+        assert_eq!(rounding.round(61), 61);
+    }
+}
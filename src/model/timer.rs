@@ -1,8 +1,14 @@
 use serde::{Deserialize, Serialize};
 
+use super::billing::Billing;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Timer {
     pub id: u32,
     pub start_time: u64,
-    pub(crate) end_time: Option<u64>
-}
\ No newline at end of file
+    pub(crate) end_time: Option<u64>,
+    #[serde(default)]
+    pub note: Option<String>,
+    #[serde(default, skip_serializing_if = "Billing::is_empty")]
+    pub billing: Billing,
+}
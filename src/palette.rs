@@ -0,0 +1,106 @@
+use std::cmp::Reverse;
+
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+use ratatui::widgets::ListState;
+use tui_input::Input;
+
+use crate::{action::Action, keymap::Keymap, model::project::Project};
+
+pub struct PaletteEntry {
+    pub label: String,
+    pub keys: String,
+    pub action: Action,
+    // Project to select before running the action.
+    pub project: Option<u64>,
+}
+
+pub struct CommandPalette {
+    pub input: Input,
+    pub entries: Vec<PaletteEntry>,
+    // Indexes of the entries matching the input, best match first.
+    pub matches: Vec<usize>,
+    pub state: ListState,
+    matcher: SkimMatcherV2,
+}
+
+impl CommandPalette {
+    // Starting a timer is listed once per project, the other commands work on the selected one.
+    pub fn new(keymap: &Keymap, projects: &[Project]) -> Self {
+        let mut entries = Vec::new();
+        for action in Action::COMMANDS {
+            let keys = keymap.describe(action);
+            if action == Action::StartTimer {
+                entries.extend(projects.iter().map(|project| PaletteEntry {
+                    label: format!("Start timer on {}", project.name),
+                    keys: keys.clone(),
+                    action,
+                    project: Some(project.id),
+                }));
+            } else {
+                entries.push(PaletteEntry {
+                    label: action.description().to_string(),
+                    keys,
+                    action,
+                    project: None,
+                });
+            }
+        }
+
+        let mut palette = Self {
+            input: Input::default(),
+            entries,
+            matches: Vec::new(),
+            state: ListState::default(),
+            matcher: SkimMatcherV2::default().ignore_case(),
+        };
+        palette.update_matches();
+        palette
+    }
+
+    pub fn update_matches(&mut self) {
+        let pattern = self.input.value();
+        let mut scored: Vec<(i64, usize)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, entry)| {
+                self.matcher
+                    .fuzzy_match(&entry.label, pattern)
+                    .map(|score| (score, i))
+            })
+            .collect();
+        // Stable, so entries with equal scores keep their order.
+        scored.sort_by_key(|(score, _)| Reverse(*score));
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+        self.state.select(if self.matches.is_empty() {
+            None
+        } else {
+            Some(0)
+        });
+    }
+
+    pub fn next(&mut self) {
+        if !self.matches.is_empty() {
+            let i = self
+                .state
+                .selected()
+                .map_or(0, |i| (i + 1) % self.matches.len());
+            self.state.select(Some(i));
+        }
+    }
+
+    pub fn previous(&mut self) {
+        if !self.matches.is_empty() {
+            let len = self.matches.len();
+            let i = self.state.selected().map_or(0, |i| (i + len - 1) % len);
+            self.state.select(Some(i));
+        }
+    }
+
+    pub fn selected(&self) -> Option<&PaletteEntry> {
+        self.state
+            .selected()
+            .and_then(|i| self.matches.get(i))
+            .map(|i| &self.entries[*i])
+    }
+}
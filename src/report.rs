@@ -0,0 +1,457 @@
+use chrono::{Datelike, IsoWeek, NaiveDate};
+
+use crate::{
+    datetime::Zone,
+    error::TimerError,
+    export::ExportFilter,
+    model::{
+        billing::{add_money, Billing, Money},
+        project::Project,
+        rounding::{Rounding, RoundingScope},
+    },
+};
+
+// The part of a timer within one day; timers running over midnight have one per day.
+#[derive(Debug, Clone)]
+pub struct ReportEntry {
+    pub project_id: u64,
+    pub project: String,
+    pub timer_id: u32,
+    pub start_time: u64,
+    pub end_time: Option<u64>,
+    pub duration: u64,
+    pub rounded_duration: u64,
+    pub note: Option<String>,
+    pub billing: Billing,
+    pub amount: Option<Money>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DayReport {
+    pub date: NaiveDate,
+    pub durations: Vec<u64>,
+    pub rounded_durations: Vec<u64>,
+    pub entries: Vec<ReportEntry>,
+}
+
+impl DayReport {
+    pub fn total(&self) -> u64 {
+        self.durations.iter().sum()
+    }
+
+    pub fn rounded_total(&self) -> u64 {
+        self.rounded_durations.iter().sum()
+    }
+
+    fn apply_rounding(&mut self, project_ids: &[u64], roundings: &[Rounding]) {
+        for (column, (project_id, rounding)) in project_ids.iter().zip(roundings).enumerate() {
+            let mut entries: Vec<&mut ReportEntry> = self
+                .entries
+                .iter_mut()
+                .filter(|entry| entry.project_id == *project_id)
+                .collect();
+
+            match rounding.scope {
+                RoundingScope::Entry => entries
+                    .iter_mut()
+                    .for_each(|entry| entry.rounded_duration = rounding.round(entry.duration)),
+                RoundingScope::Day => {
+                    // Difference between rounded and raw day total is carried by the latest
+                    // (billable first) entries, so entry amounts still add up to the day total.
+                    entries.sort_by_key(|entry| (entry.billing.is_billable(), entry.start_time));
+                    let raw = self.durations[column];
+                    let mut difference = rounding.round(raw) as i64 - raw as i64;
+                    for entry in entries.iter_mut().rev() {
+                        let rounded = (entry.duration as i64 + difference).max(0);
+                        difference -= rounded - entry.duration as i64;
+                        entry.rounded_duration = rounded as u64;
+                    }
+                }
+            }
+
+            self.rounded_durations[column] =
+                entries.iter().map(|entry| entry.rounded_duration).sum();
+        }
+
+        for entry in self.entries.iter_mut() {
+            entry.amount = entry.billing.amount(entry.rounded_duration);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WeekReport<'a> {
+    pub week: IsoWeek,
+    pub days: &'a [DayReport],
+}
+
+impl WeekReport<'_> {
+    pub fn durations(&self) -> Vec<u64> {
+        sum_columns(self.days, |day| &day.durations)
+    }
+
+    pub fn rounded_durations(&self) -> Vec<u64> {
+        sum_columns(self.days, |day| &day.rounded_durations)
+    }
+
+    pub fn total(&self) -> u64 {
+        self.days.iter().map(DayReport::total).sum()
+    }
+
+    pub fn rounded_total(&self) -> u64 {
+        self.days.iter().map(DayReport::rounded_total).sum()
+    }
+}
+
+// One column per project, keyed by `project_ids`; `projects` holds their names.
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    pub project_ids: Vec<u64>,
+    pub projects: Vec<String>,
+    pub days: Vec<DayReport>,
+    pub rounded: bool,
+}
+
+impl Report {
+    pub fn build(
+        projects: &[Project],
+        filter: &ExportFilter,
+        zone: &Zone,
+        rounding: &Rounding,
+        now: u64,
+    ) -> Result<Self, TimerError> {
+        // Timers running over midnight are split, so each day only gets its own part. Timers
+        // outside the filtered dates are skipped before that.
+        let (from, to) = filter.time_range(zone);
+        let mut columns = Vec::new();
+        for project in projects
+            .iter()
+            .filter(|project| filter.matches_project(project))
+        {
+            let mut segments = Vec::new();
+            for timer in &project.timers {
+                let end = timer.end_time.unwrap_or(now).max(timer.start_time);
+                if timer.start_time >= to || end < from {
+                    continue;
+                }
+                let parts = zone.split_by_day(timer.start_time, end)?;
+                for (date, start, end) in parts {
+                    if filter.matches_date(date) {
+                        // Parts of a running timer have no end yet, even the ones before
+                        // midnight.
+                        let end_time = timer.end_time.map(|_| end);
+                        segments.push((timer, date, start, end, end_time));
+                    }
+                }
+            }
+            if !segments.is_empty() {
+                columns.push((project, segments));
+            }
+        }
+        // Projects with the same name keep a column each, in the order of their ids.
+        columns.sort_by_key(|(project, _)| (project.name.to_lowercase(), project.id));
+        let roundings: Vec<Rounding> = columns
+            .iter()
+            .map(|(project, _)| project.rounding.unwrap_or(*rounding))
+            .collect();
+
+        let mut days: Vec<DayReport> = Vec::new();
+        for (column, (project, segments)) in columns.iter().enumerate() {
+            for (timer, date, start, end, end_time) in segments {
+                let index = match days.iter().position(|day| day.date == *date) {
+                    Some(index) => index,
+                    None => {
+                        days.push(DayReport {
+                            date: *date,
+                            durations: vec![0; columns.len()],
+                            rounded_durations: vec![0; columns.len()],
+                            entries: Vec::new(),
+                        });
+                        days.len() - 1
+                    }
+                };
+
+                let duration = end - start;
+                let day = &mut days[index];
+                day.durations[column] += duration;
+                day.entries.push(ReportEntry {
+                    project_id: project.id,
+                    project: project.name.clone(),
+                    timer_id: timer.id,
+                    start_time: *start,
+                    end_time: *end_time,
+                    duration,
+                    rounded_duration: duration,
+                    note: timer.note.clone(),
+                    billing: project.get_billing(timer),
+                    amount: None,
+                });
+            }
+        }
+
+        let project_ids: Vec<u64> = columns.iter().map(|(project, _)| project.id).collect();
+        days.sort_by_key(|day| day.date);
+        for day in days.iter_mut() {
+            day.entries.sort_by_key(|entry| entry.start_time);
+            day.apply_rounding(&project_ids, &roundings);
+        }
+
+        Ok(Self {
+            rounded: roundings.iter().any(|rounding| !rounding.is_none()),
+            projects: columns
+                .iter()
+                .map(|(project, _)| project.name.clone())
+                .collect(),
+            project_ids,
+            days,
+        })
+    }
+
+    pub fn weeks(&self) -> Vec<WeekReport<'_>> {
+        self.days
+            .chunk_by(|a, b| a.date.iso_week() == b.date.iso_week())
+            .map(|days| WeekReport {
+                week: days[0].date.iso_week(),
+                days,
+            })
+            .collect()
+    }
+
+    pub fn durations(&self) -> Vec<u64> {
+        sum_columns(&self.days, |day| &day.durations)
+    }
+
+    pub fn rounded_durations(&self) -> Vec<u64> {
+        sum_columns(&self.days, |day| &day.rounded_durations)
+    }
+
+    pub fn total(&self) -> u64 {
+        self.days.iter().map(DayReport::total).sum()
+    }
+
+    pub fn rounded_total(&self) -> u64 {
+        self.days.iter().map(DayReport::rounded_total).sum()
+    }
+
+    pub fn amounts(&self) -> Vec<Vec<Money>> {
+        self.project_ids
+            .iter()
+            .map(|project_id| {
+                let mut totals = Vec::new();
+                self.entries()
+                    .filter(|entry| entry.project_id == *project_id)
+                    .filter_map(|entry| entry.amount.as_ref())
+                    .for_each(|amount| add_money(&mut totals, amount));
+                totals
+            })
+            .collect()
+    }
+
+    pub fn total_amounts(&self) -> Vec<Money> {
+        let mut totals = Vec::new();
+        self.entries()
+            .filter_map(|entry| entry.amount.as_ref())
+            .for_each(|amount| add_money(&mut totals, amount));
+        totals
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &ReportEntry> {
+        self.days.iter().flat_map(|day| day.entries.iter())
+    }
+
+    pub fn total_since(&self, date: NaiveDate) -> u64 {
+        self.days
+            .iter()
+            .filter(|day| day.date >= date)
+            .map(DayReport::total)
+            .sum()
+    }
+}
+
+fn sum_columns(days: &[DayReport], durations: fn(&DayReport) -> &Vec<u64>) -> Vec<u64> {
+    let columns = days.first().map(|day| durations(day).len()).unwrap_or(0);
+    (0..columns)
+        .map(|column| days.iter().map(|day| durations(day)[column]).sum())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        model::{billing::Cents, rounding::RoundingMode, timer::Timer},
+        test_support::{project, timer, NOW},
+    };
+
+    const MINUTE: u64 = 60;
+
+    // Alpha at 60.00 an hour: billable 20 minutes at NOW, 5 minutes not billed an hour later
+    // and billable 4 minutes two hours later.
+    fn report(mode: RoundingMode, scope: RoundingScope) -> Report {
+        let not_billed = Timer {
+            billing: Billing {
+                billable: Some(false),
+                ..Billing::default()
+            },
+            ..timer(2, NOW + 60 * MINUTE, Some(NOW + 65 * MINUTE))
+        };
+        let mut alpha = project(
+            1,
+            "Alpha",
+            vec![
+                timer(1, NOW, Some(NOW + 20 * MINUTE)),
+                not_billed,
+                timer(3, NOW + 120 * MINUTE, Some(NOW + 124 * MINUTE)),
+            ],
+        );
+        alpha.billing.hourly_rate = Some(Cents(6000));
+        let rounding = Rounding {
+            mode,
+            increment: 15,
+            scope,
+        };
+        Report::build(
+            &[alpha],
+            &ExportFilter::default(),
+            &Zone::Utc,
+            &rounding,
+            NOW,
+        )
+        .unwrap()
+    }
+
+    fn rounded_minutes(report: &Report) -> Vec<u64> {
+        report
+            .entries()
+            .map(|entry| entry.rounded_duration / MINUTE)
+            .collect()
+    }
+
+    fn amounts(report: &Report) -> Vec<Option<Cents>> {
+        report
+            .entries()
+            .map(|entry| entry.amount.as_ref().map(|amount| amount.amount))
+            .collect()
+    }
+
+    #[test]
+    fn entry_scope_rounds_each_entry() {
+        let report = report(RoundingMode::Up, RoundingScope::Entry);
+
+        assert_eq!(rounded_minutes(&report), [30, 15, 15]);
+        assert_eq!(report.rounded_total(), 60 * MINUTE);
+        assert_eq!(
+            amounts(&report),
+            [Some(Cents(3000)), None, Some(Cents(1500))]
+        );
+    }
+
+    #[test]
+    fn day_scope_gives_difference_to_latest_billable_entry() {
+        // 29 minutes round to 30, the extra minute goes to the 4 minute entry.
+        let report = report(RoundingMode::Nearest, RoundingScope::Day);
+
+        assert_eq!(rounded_minutes(&report), [20, 5, 5]);
+        assert_eq!(report.rounded_total(), 30 * MINUTE);
+        assert_eq!(
+            amounts(&report),
+            [Some(Cents(2000)), None, Some(Cents(500))]
+        );
+    }
+
+    #[test]
+    fn day_scope_carries_what_an_entry_cannot_give_up() {
+        // 29 minutes round down to 15: the 4 minute entry drops to 0 and the other 10 minutes
+        // come off the earlier billable entry, the one not billed keeps its time.
+        let report = report(RoundingMode::Down, RoundingScope::Day);
+
+        assert_eq!(rounded_minutes(&report), [10, 5, 0]);
+        assert_eq!(report.rounded_total(), 15 * MINUTE);
+        assert_eq!(amounts(&report), [Some(Cents(1000)), None, Some(Cents(0))]);
+    }
+
+    #[test]
+    fn project_rounding_overrides_the_given_one() {
+        let mut alpha = project(1, "Alpha", vec![timer(1, NOW, Some(NOW + 7 * MINUTE))]);
+        alpha.rounding = Some(Rounding {
+            mode: RoundingMode::Up,
+            ..Rounding::default()
+        });
+        let report = Report::build(
+            &[alpha],
+            &ExportFilter::default(),
+            &Zone::Utc,
+            &Rounding::default(),
+            NOW,
+        )
+        .unwrap();
+
+        assert!(report.rounded);
+        assert_eq!(rounded_minutes(&report), [15]);
+    }
+
+    #[test]
+    fn running_timer_over_midnight_stays_open_on_each_day() {
+        let alpha = project(1, "Alpha", vec![timer(1, NOW + 10 * 60 * MINUTE, None)]);
+        let report = Report::build(
+            &[alpha],
+            &ExportFilter::default(),
+            &Zone::Utc,
+            &Rounding::default(),
+            NOW + 13 * 60 * MINUTE,
+        )
+        .unwrap();
+
+        let parts: Vec<(u64, Option<u64>)> = report
+            .entries()
+            .map(|entry| (entry.duration / MINUTE, entry.end_time))
+            .collect();
+        assert_eq!(parts, [(120, None), (60, None)]);
+    }
+
+    #[test]
+    fn projects_with_the_same_name_keep_their_own_columns() {
+        let mut rounded = project(2, "Alpha", vec![timer(1, NOW, Some(NOW + 7 * MINUTE))]);
+        rounded.rounding = Some(Rounding {
+            mode: RoundingMode::Up,
+            ..Rounding::default()
+        });
+        let plain = project(1, "Alpha", vec![timer(1, NOW, Some(NOW + 7 * MINUTE))]);
+        let report = Report::build(
+            &[rounded, plain],
+            &ExportFilter::default(),
+            &Zone::Utc,
+            &Rounding::default(),
+            NOW,
+        )
+        .unwrap();
+
+        assert_eq!(report.project_ids, [1, 2]);
+        assert_eq!(report.projects, ["Alpha", "Alpha"]);
+        assert_eq!(report.durations(), [7 * MINUTE, 7 * MINUTE]);
+        assert_eq!(report.rounded_durations(), [7 * MINUTE, 15 * MINUTE]);
+    }
+
+    #[test]
+    fn timers_outside_the_dates_are_skipped_before_splitting() {
+        // Splitting the second timer would fail, so it must not be reached.
+        let alpha = project(
+            1,
+            "Alpha",
+            vec![
+                timer(1, NOW, Some(NOW + 60 * MINUTE)),
+                timer(2, u64::MAX - 1, Some(u64::MAX)),
+            ],
+        );
+        let today = NaiveDate::from_ymd_opt(2024, 3, 15);
+        let filter = ExportFilter {
+            from: today,
+            to: today,
+            ..ExportFilter::default()
+        };
+        let report =
+            Report::build(&[alpha], &filter, &Zone::Utc, &Rounding::default(), NOW).unwrap();
+
+        assert_eq!(report.total(), 60 * MINUTE);
+    }
+}
@@ -1,55 +1,78 @@
-use std::{
-    fs,
-    path::Path,
-    time::{SystemTime, UNIX_EPOCH},
-};
-
-use serde::{Deserialize, Serialize};
+use std::{path::Path, rc::Rc};
 
 use crate::{
+    clock::Clock,
     error::TimerError,
-    model::project::Project,
+    import::{ImportSummary, ImportedEntry},
+    model::{billing::Billing, project::Project, timer::Timer},
+    storage::{self, journal::JournalEntry, lock::WriteLock, ProjectsData, Storage, StorageKind},
 };
 
 pub struct Repository {
     file_name: String,
+    storage: Box<dyn Storage>,
     projects: Vec<Project>,
     favorites: Vec<u64>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct ProjectsData {
-    projects: Vec<Project>,
-    favorites: Vec<u64>,
+    invoice_counter: u32,
+    clock: Rc<dyn Clock>,
 }
 
 impl Repository {
-    pub fn new(file_name: &str) -> Self {
-        let mut repo = Self {
-            file_name: file_name.to_string(),
-            projects: Vec::new(),
-            favorites: Vec::new(),
+    pub fn open(
+        file_name: &str,
+        kind: StorageKind,
+        clock: Rc<dyn Clock>,
+    ) -> Result<Self, TimerError> {
+        let mut storage = storage::open(kind, Path::new(file_name), clock.clone())?;
+        // Loading may write too, when the data is migrated.
+        let data = {
+            let _lock = WriteLock::acquire(Path::new(file_name))?;
+            storage.load()?
         };
-        repo.load_projects_from_file();
-        repo
+        Ok(Self {
+            file_name: file_name.to_string(),
+            storage,
+            projects: data.projects,
+            favorites: data.favorites,
+            invoice_counter: data.invoice_counter,
+            clock,
+        })
     }
 
-    fn load_projects_from_file(&mut self) {
-        let path = Path::new(&self.file_name);
-        if path.exists() {
-            if let Ok(data) = fs::read_to_string(path) {
-                if let Ok(parsed) = serde_json::from_str::<ProjectsData>(&data) {
-                    self.projects = parsed.projects;
-                    self.favorites = parsed.favorites;
-                }
-            }
-        }
+    pub fn file_name(&self) -> &str {
+        &self.file_name
+    }
+
+    pub fn clock(&self) -> Rc<dyn Clock> {
+        self.clock.clone()
+    }
+
+    pub fn now(&self) -> u64 {
+        self.clock.now()
     }
 
     pub fn find_all(&self) -> &Vec<Project> {
         &self.projects
     }
 
+    // Projects with only their timers overlapping `from..to`, looked up by the storage.
+    pub fn find_between(&self, from: u64, to: u64) -> Result<Vec<Project>, TimerError> {
+        let timers = self.storage.timers_between(from, to)?;
+        Ok(self
+            .projects
+            .iter()
+            .map(|project| Project {
+                timers: timers
+                    .iter()
+                    .filter(|(project_id, _)| *project_id == project.id)
+                    .map(|(_, timer)| timer.clone())
+                    .collect(),
+                ..project.clone()
+            })
+            .collect())
+    }
+
+    #[allow(dead_code)]
     fn find_favorites(&self) -> Vec<&Project> {
         self.projects
             .iter()
@@ -58,51 +81,468 @@ impl Repository {
     }
 
     pub fn delete_project(&mut self, project_id: u64) -> Result<bool, TimerError> {
-        if let Some(index) = self.projects.iter().position(|x| x.id == project_id) {
-            self.projects.remove(index);
-            self.save();
+        self.locked(|repository| {
+            let Some(index) = repository.projects.iter().position(|x| x.id == project_id) else {
+                return Err(TimerError::new("Project with given id does not exists."));
+            };
+            repository.storage.delete_project(project_id)?;
+            repository.projects.remove(index);
+            repository.favorites.retain(|x| *x != project_id);
             Ok(true)
-        } else {
-            Err(TimerError::new("Project with given id does not exists."))
-        }
+        })
     }
 
     pub fn add_project(&mut self, project_name: String) -> Result<bool, TimerError> {
-        let project = Project {
-            id: SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
-            name: project_name,
-            timers: Vec::new(),
-        };
-        self.projects.push(project);
-        self.save();
-        Ok(true)
+        self.locked(|repository| {
+            let project = Project::new(
+                next_project_id(&repository.projects, repository.now()),
+                project_name,
+            );
+            repository.storage.save_project(&project)?;
+            repository.projects.push(project);
+            Ok(true)
+        })
+    }
+
+    // `change` gets the project as it is in the data file, so what other programs changed on
+    // it in the meantime (like a timer they started) is kept.
+    pub fn update_project(
+        &mut self,
+        project_id: u64,
+        change: impl FnOnce(&mut Project) -> Result<(), TimerError>,
+    ) -> Result<bool, TimerError> {
+        self.locked(|repository| {
+            let Some(index) = repository.projects.iter().position(|x| x.id == project_id) else {
+                return Err(TimerError::new("Project with given id does not exists."));
+            };
+            let mut project = repository.projects[index].clone();
+            change(&mut project)?;
+            repository.storage.save_project(&project)?;
+            repository.projects[index] = project;
+            Ok(true)
+        })
+    }
+
+    // Only one timer runs at a time, so a timer running on another project is stopped first.
+    pub fn start_timer(&mut self, project_id: u64) -> Result<bool, TimerError> {
+        self.locked(|repository| {
+            let Some(index) = repository.projects.iter().position(|x| x.id == project_id) else {
+                return Err(TimerError::new("Project with given id does not exists."));
+            };
+            if repository.projects[index]
+                .timers
+                .iter()
+                .any(|x| x.end_time.is_none())
+            {
+                return Err(TimerError::new("Timer is already running on this project."));
+            }
+
+            let now = repository.now();
+            for (project_id, timer) in stop_running_timers(&mut repository.projects, now) {
+                repository.storage.save_timer(project_id, &timer)?;
+            }
+            let project = &mut repository.projects[index];
+            let timer = Timer {
+                id: project.timers.iter().map(|x| x.id).max().unwrap_or(0) + 1,
+                start_time: now,
+                end_time: None,
+                note: None,
+                billing: Billing::default(),
+            };
+            repository.storage.save_timer(project_id, &timer)?;
+            project.timers.push(timer);
+            Ok(true)
+        })
     }
 
-    pub fn edit_project(&mut self, project: Project) -> Result<bool, TimerError> {
-        if let Some(index) = self.projects.iter().position(|x| x.id == project.id) {
-            self.projects[index] = project;
-            self.save();
+    pub fn stop_timer(&mut self) -> Result<bool, TimerError> {
+        self.locked(|repository| {
+            let now = repository.now();
+            let stopped = stop_running_timers(&mut repository.projects, now);
+            if stopped.is_empty() {
+                return Err(TimerError::new("No timer is running."));
+            }
+            for (project_id, timer) in stopped {
+                repository.storage.save_timer(project_id, &timer)?;
+            }
             Ok(true)
-        } else {
-            Err(TimerError::new("Project with given id does not exists."))
+        })
+    }
+
+    // Hands the next invoice number to `issue` and only uses it up once the invoice is
+    // written, so a failed write leaves no gap in the numbers.
+    pub fn issue_invoice<T>(
+        &mut self,
+        issue: impl FnOnce(u32) -> Result<T, TimerError>,
+    ) -> Result<T, TimerError> {
+        self.locked(|repository| {
+            let number = repository.invoice_counter + 1;
+            let issued = issue(number)?;
+            repository.storage.save_invoice_counter(number)?;
+            repository.invoice_counter = number;
+            Ok(issued)
+        })
+    }
+
+    pub fn import(
+        &mut self,
+        entries: Vec<ImportedEntry>,
+        dry_run: bool,
+    ) -> Result<ImportSummary, TimerError> {
+        self.locked(|repository| repository.import_entries(entries, dry_run))
+    }
+
+    // Each new project and timer is saved as a change of its own, like ones made by hand.
+    // Entries still running are skipped, as only one timer runs at a time.
+    fn import_entries(
+        &mut self,
+        entries: Vec<ImportedEntry>,
+        dry_run: bool,
+    ) -> Result<ImportSummary, TimerError> {
+        let mut projects = self.projects.clone();
+        let mut created = Vec::new();
+        let mut timers = Vec::new();
+        let mut summary = ImportSummary::default();
+
+        for entry in entries {
+            if entry.end_time.is_none() {
+                summary.running += 1;
+                continue;
+            }
+            let index = match projects
+                .iter()
+                .position(|x| x.name.eq_ignore_ascii_case(&entry.project))
+            {
+                Some(index) => index,
+                None => {
+                    let project = Project::new(
+                        next_project_id(&projects, self.now()),
+                        entry.project.clone(),
+                    );
+                    created.push(project.clone());
+                    projects.push(project);
+                    summary.created_projects.push(entry.project.clone());
+                    projects.len() - 1
+                }
+            };
+
+            let project = &mut projects[index];
+            if project
+                .timers
+                .iter()
+                .any(|x| x.start_time == entry.start_time && x.end_time == entry.end_time)
+            {
+                summary.duplicates += 1;
+                continue;
+            }
+
+            let timer = Timer {
+                id: project.timers.iter().map(|x| x.id).max().unwrap_or(0) + 1,
+                start_time: entry.start_time,
+                end_time: entry.end_time,
+                note: entry.note.clone(),
+                billing: Billing::default(),
+            };
+            project.timers.push(timer.clone());
+            timers.push((project.id, timer));
+            summary.added.push(entry);
         }
-    } 
 
-    pub fn save(&self) {
-        let data = ProjectsData {
+        if !dry_run {
+            for project in created {
+                self.storage.save_project(&project)?;
+                self.projects.push(project);
+            }
+            for (project_id, timer) in timers {
+                self.storage.save_timer(project_id, &timer)?;
+                if let Some(project) = self.projects.iter_mut().find(|x| x.id == project_id) {
+                    project.timers.push(timer);
+                }
+            }
+        }
+        Ok(summary)
+    }
+
+    pub fn history(&self) -> Result<Vec<JournalEntry>, TimerError> {
+        self.storage.history()
+    }
+
+    // Writes all data to another storage, e.g. when moving to a different backend.
+    pub fn copy_to(&self, storage: &mut dyn Storage) -> Result<(), TimerError> {
+        storage.save(&self.data())
+    }
+
+    // Runs a change with the data file locked, on top of what other programs saved in the
+    // meantime, so their changes are merged instead of overwritten.
+    fn locked<T>(
+        &mut self,
+        change: impl FnOnce(&mut Self) -> Result<T, TimerError>,
+    ) -> Result<T, TimerError> {
+        let _lock = WriteLock::acquire(Path::new(&self.file_name))?;
+        self.reload_if_changed()?;
+        change(self)
+    }
+
+    // Reads what other programs saved since the data was last read; returns whether there was
+    // anything.
+    pub fn refresh(&mut self) -> Result<bool, TimerError> {
+        let _lock = WriteLock::acquire(Path::new(&self.file_name))?;
+        self.reload_if_changed()
+    }
+
+    // Returns whether there was anything to reload.
+    fn reload_if_changed(&mut self) -> Result<bool, TimerError> {
+        if !self.storage.changed()? {
+            return Ok(false);
+        }
+        let data = self.storage.load()?;
+        self.projects = data.projects;
+        self.favorites = data.favorites;
+        self.invoice_counter = data.invoice_counter;
+        Ok(true)
+    }
+
+    fn data(&self) -> ProjectsData {
+        ProjectsData {
             projects: self.projects.clone(),
             favorites: self.favorites.clone(),
-        };
+            invoice_counter: self.invoice_counter,
+            ..ProjectsData::default()
+        }
+    }
+}
 
-        if let Ok(json) = serde_json::to_string(&data) {
-            if let Err(e) = fs::write(&self.file_name, json) {
-                eprintln!("Failed to write to file: {}", e);
+// Returns the stopped timers with their project ids.
+fn stop_running_timers(projects: &mut [Project], now: u64) -> Vec<(u64, Timer)> {
+    let mut stopped = Vec::new();
+    for project in projects.iter_mut() {
+        for timer in project.timers.iter_mut() {
+            if timer.end_time.is_none() {
+                timer.end_time = Some(now.max(timer.start_time));
+                stopped.push((project.id, timer.clone()));
             }
-        } else {
-            eprintln!("Failed to serialize data");
         }
     }
+    stopped
+}
+
+fn next_project_id(projects: &[Project], now: u64) -> u64 {
+    let max_id = projects.iter().map(|x| x.id + 1).max().unwrap_or(0);
+    now.max(max_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        clock::FixedClock,
+        test_support::{DataFile, NOW},
+    };
+
+    fn repository(file: &DataFile, clock: &Rc<FixedClock>) -> Repository {
+        Repository::open(&file.path(), StorageKind::Json, clock.clone()).unwrap()
+    }
+
+    fn timers(repository: &Repository, project: usize) -> Vec<(u64, Option<u64>)> {
+        repository.find_all()[project]
+            .timers
+            .iter()
+            .map(|timer| (timer.start_time, timer.end_time))
+            .collect()
+    }
+
+    #[test]
+    fn start_and_stop_use_clock() {
+        let file = DataFile::with_projects("repository_start_stop", &[("Alpha", &[])]);
+        let clock = Rc::new(FixedClock::new(NOW));
+        let mut repository = repository(&file, &clock);
+
+        repository.start_timer(1).unwrap();
+        clock.advance(90 * 60);
+        repository.stop_timer().unwrap();
+
+        assert_eq!(timers(&repository, 0), [(NOW, Some(NOW + 90 * 60))]);
+    }
+
+    #[test]
+    fn starting_timer_stops_running_one() {
+        let file = DataFile::with_projects(
+            "repository_switch",
+            &[("Alpha", &[(NOW - 600, None)]), ("Beta", &[])],
+        );
+        let clock = Rc::new(FixedClock::new(NOW));
+        let mut repository = repository(&file, &clock);
+
+        repository.start_timer(2).unwrap();
+
+        assert_eq!(timers(&repository, 0), [(NOW - 600, Some(NOW))]);
+        assert_eq!(timers(&repository, 1), [(NOW, None)]);
+        assert!(repository.start_timer(2).is_err());
+    }
+
+    #[test]
+    fn stopping_without_running_timer_fails() {
+        let file = DataFile::with_projects("repository_stop", &[("Alpha", &[(NOW, Some(NOW))])]);
+        let clock = Rc::new(FixedClock::new(NOW));
+        let mut repository = repository(&file, &clock);
+
+        assert!(repository.stop_timer().is_err());
+    }
+
+    #[test]
+    fn changes_are_saved() {
+        let file = DataFile::new("repository_saved");
+        let clock = Rc::new(FixedClock::new(NOW));
+        let mut repository = repository(&file, &clock);
+        repository.add_project("Alpha".to_string()).unwrap();
+        repository.start_timer(NOW).unwrap();
+
+        let reloaded = Repository::open(&file.path(), StorageKind::Json, clock.clone()).unwrap();
+        assert_eq!(reloaded.find_all()[0].name, "Alpha");
+        assert_eq!(timers(&reloaded, 0), [(NOW, None)]);
+    }
+
+    #[test]
+    fn find_between_keeps_overlapping_timers() {
+        let file = DataFile::with_projects(
+            "repository_between",
+            &[
+                ("Alpha", &[(NOW - 7200, Some(NOW - 3600)), (NOW - 600, None)]),
+                ("Beta", &[(NOW - 3000, Some(NOW - 1200))]),
+            ],
+        );
+        let clock = Rc::new(FixedClock::new(NOW));
+        let repository = repository(&file, &clock);
+
+        let spans: Vec<Vec<(u64, Option<u64>)>> = repository
+            .find_between(NOW - 1800, NOW)
+            .unwrap()
+            .iter()
+            .map(|project| project.timers.iter().map(|x| (x.start_time, x.end_time)).collect())
+            .collect();
+        assert_eq!(spans, [vec![(NOW - 600, None)], vec![(NOW - 3000, Some(NOW - 1200))]]);
+    }
+
+    #[test]
+    fn import_skips_duplicates_and_creates_missing_projects() {
+        let file =
+            DataFile::with_projects("repository_import", &[("Alpha", &[(NOW, Some(NOW + 60))])]);
+        let clock = Rc::new(FixedClock::new(NOW));
+        let mut repository = repository(&file, &clock);
+        let entry = |project: &str, start_time: u64| ImportedEntry {
+            project: project.to_string(),
+            start_time,
+            end_time: Some(start_time + 60),
+            note: None,
+        };
+        let entries = vec![entry("alpha", NOW), entry("Alpha", NOW + 120), entry("Beta", NOW)];
+
+        let preview = repository.import(entries.clone(), true).unwrap();
+        assert_eq!((preview.added.len(), preview.duplicates), (2, 1));
+        assert_eq!(repository.find_all().len(), 1);
+
+        let summary = repository.import(entries.clone(), false).unwrap();
+        assert_eq!(summary.created_projects, ["Beta"]);
+        assert_eq!(timers(&repository, 0), [(NOW, Some(NOW + 60)), (NOW + 120, Some(NOW + 180))]);
+        assert_eq!(timers(&repository, 1), [(NOW, Some(NOW + 60))]);
+        let events: Vec<String> = repository
+            .history()
+            .unwrap()
+            .iter()
+            .map(|entry| entry.event.describe(repository.find_all()))
+            .collect();
+        assert_eq!(
+            events[events.len() - 3..],
+            [
+                "Created project Beta",
+                "Started timer 2 on Alpha",
+                "Started timer 1 on Beta"
+            ]
+        );
+
+        let again = repository.import(entries, false).unwrap();
+        assert_eq!((again.added.len(), again.duplicates), (0, 3));
+    }
+
+    #[test]
+    fn import_skips_running_entries() {
+        let file = DataFile::with_projects("repository_import_running", &[("Alpha", &[(NOW, None)])]);
+        let clock = Rc::new(FixedClock::new(NOW + 60));
+        let mut repository = repository(&file, &clock);
+        let entries = vec![ImportedEntry {
+            project: "Beta".to_string(),
+            start_time: NOW - 60,
+            end_time: None,
+            note: None,
+        }];
+
+        let summary = repository.import(entries, false).unwrap();
+        assert_eq!((summary.added.len(), summary.running), (0, 1));
+        assert!(summary.created_projects.is_empty());
+        assert_eq!(repository.find_all().len(), 1);
+        assert_eq!(timers(&repository, 0), [(NOW, None)]);
+    }
+
+    #[test]
+    fn failed_invoice_keeps_its_number() {
+        let file = DataFile::new("repository_invoice");
+        let clock = Rc::new(FixedClock::new(NOW));
+        let mut repository = repository(&file, &clock);
+
+        let failed = repository.issue_invoice(|_| Err::<u32, _>(TimerError::new("Disk full")));
+        assert!(failed.is_err());
+        assert_eq!(repository.issue_invoice(Ok).unwrap(), 1);
+        assert_eq!(repository.issue_invoice(Ok).unwrap(), 2);
+        assert_eq!(self::repository(&file, &clock).issue_invoice(Ok).unwrap(), 3);
+    }
+
+    // Two repositories on one file, like two programs: the stale one picks up the other's
+    // changes before writing its own.
+    fn check_concurrent_changes(kind: StorageKind, name: &str) {
+        let file = DataFile::new(name);
+        let clock = Rc::new(FixedClock::new(NOW));
+        let open = || Repository::open(&file.path(), kind, clock.clone()).unwrap();
+        let mut first = open();
+        first.add_project("Alpha".to_string()).unwrap();
+        first.add_project("Beta".to_string()).unwrap();
+        let mut second = open();
+
+        first.start_timer(NOW).unwrap();
+        second
+            .update_project(NOW + 1, |project| {
+                project.name = "Gamma".to_string();
+                Ok(())
+            })
+            .unwrap();
+        clock.advance(60);
+        second.start_timer(NOW + 1).unwrap();
+
+        let reloaded = open();
+        let names: Vec<&str> = reloaded.find_all().iter().map(|x| x.name.as_str()).collect();
+        assert_eq!(names, ["Alpha", "Gamma"]);
+        assert_eq!(timers(&reloaded, 0), [(NOW, Some(NOW + 60))]);
+        assert_eq!(timers(&reloaded, 1), [(NOW + 60, None)]);
+    }
+
+    #[test]
+    fn concurrent_json_changes_are_merged() {
+        check_concurrent_changes(StorageKind::Json, "repository_concurrent_json");
+    }
+
+    #[test]
+    fn concurrent_sqlite_changes_are_merged() {
+        check_concurrent_changes(StorageKind::Sqlite, "repository_concurrent_sqlite");
+    }
+
+    #[test]
+    fn project_ids_follow_clock_and_stay_unique() {
+        let file = DataFile::new("repository_ids");
+        let clock = Rc::new(FixedClock::new(NOW));
+        let mut repository = repository(&file, &clock);
+        repository.add_project("Alpha".to_string()).unwrap();
+        repository.add_project("Beta".to_string()).unwrap();
+
+        let ids: Vec<u64> = repository.find_all().iter().map(|x| x.id).collect();
+        assert_eq!(ids, [NOW, NOW + 1]);
+    }
 }
@@ -0,0 +1,295 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::TimerError,
+    model::{project::Project, timer::Timer},
+};
+
+use super::{migration, sibling, ProjectsData};
+
+// A single change to the data.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    ProjectCreated {
+        project: Project,
+    },
+    ProjectEdited {
+        project: Project,
+    },
+    ProjectDeleted {
+        project_id: u64,
+    },
+    TimerStarted {
+        project_id: u64,
+        timer: Timer,
+    },
+    TimerStopped {
+        project_id: u64,
+        timer_id: u32,
+        end_time: u64,
+    },
+    TimerEdited {
+        project_id: u64,
+        timer: Timer,
+    },
+    InvoiceCounterSet {
+        invoice_counter: u32,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    // Counts up from 1 across compactions; entries written before it existed have 0.
+    #[serde(default)]
+    pub sequence: u64,
+    // Data version the entry was written in.
+    pub version: u32,
+    pub time: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+impl Event {
+    // Events of timers or projects that no longer exist are skipped.
+    pub fn apply(&self, data: &mut ProjectsData) {
+        match self {
+            Event::ProjectCreated { project } | Event::ProjectEdited { project } => {
+                match data.projects.iter_mut().find(|x| x.id == project.id) {
+                    Some(existing) => *existing = project.clone(),
+                    None => data.projects.push(project.clone()),
+                }
+            }
+            Event::ProjectDeleted { project_id } => {
+                data.projects.retain(|x| x.id != *project_id);
+                data.favorites.retain(|x| x != project_id);
+            }
+            Event::TimerStarted { project_id, timer }
+            | Event::TimerEdited { project_id, timer } => {
+                if let Some(project) = data.projects.iter_mut().find(|x| x.id == *project_id) {
+                    match project.timers.iter_mut().find(|x| x.id == timer.id) {
+                        Some(existing) => *existing = timer.clone(),
+                        None => project.timers.push(timer.clone()),
+                    }
+                }
+            }
+            Event::TimerStopped {
+                project_id,
+                timer_id,
+                end_time,
+            } => {
+                if let Some(timer) = data
+                    .projects
+                    .iter_mut()
+                    .filter(|x| x.id == *project_id)
+                    .flat_map(|x| x.timers.iter_mut())
+                    .find(|x| x.id == *timer_id)
+                {
+                    timer.end_time = Some(*end_time);
+                }
+            }
+            Event::InvoiceCounterSet { invoice_counter } => {
+                data.invoice_counter = *invoice_counter;
+            }
+        }
+    }
+
+    // One line for the history, with project names looked up in `projects` when the event
+    // doesn't carry them.
+    pub fn describe(&self, projects: &[Project]) -> String {
+        let name = |project_id: &u64| {
+            projects
+                .iter()
+                .find(|x| x.id == *project_id)
+                .map(|x| x.name.clone())
+                .unwrap_or_else(|| format!("project {}", project_id))
+        };
+        match self {
+            Event::ProjectCreated { project } => format!("Created project {}", project.name),
+            Event::ProjectEdited { project } => format!("Edited project {}", project.name),
+            Event::ProjectDeleted { project_id } => format!("Deleted {}", name(project_id)),
+            Event::TimerStarted { project_id, timer } => {
+                format!("Started timer {} on {}", timer.id, name(project_id))
+            }
+            Event::TimerStopped {
+                project_id,
+                timer_id,
+                ..
+            } => format!("Stopped timer {} on {}", timer_id, name(project_id)),
+            Event::TimerEdited { project_id, timer } => {
+                format!("Edited timer {} on {}", timer.id, name(project_id))
+            }
+            Event::InvoiceCounterSet { invoice_counter } => {
+                format!("Used invoice number {}", invoice_counter)
+            }
+        }
+    }
+}
+
+// Changes since the last snapshot, one JSON entry per line in `<data file>.journal`. When the
+// journal is compacted its entries move to `<data file>.history`, which keeps all of them.
+// The snapshot records the sequence number of the last entry it has, so entries left over
+// when compaction is cut short are not applied again.
+pub struct Journal {
+    path: PathBuf,
+    history_path: PathBuf,
+    len: usize,
+    sequence: u64,
+}
+
+impl Journal {
+    pub fn new(data_file: &Path) -> Self {
+        Self {
+            path: sibling(data_file, "journal"),
+            history_path: sibling(data_file, "history"),
+            len: 0,
+            sequence: 0,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Entries not folded into the snapshot yet.
+    pub fn pending(&self) -> usize {
+        self.len
+    }
+
+    // Sequence number of the last entry written.
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    // Entries written completely that come after the snapshot's `snapshot_sequence`. A line
+    // cut short by a crash is dropped from the file, so new entries don't get appended to it.
+    pub fn read(&mut self, snapshot_sequence: u64) -> Result<Vec<JournalEntry>, TimerError> {
+        let entries = match fs::read_to_string(&self.path) {
+            Ok(content) => {
+                let complete = complete_lines(&content);
+                if complete.len() < content.len() {
+                    OpenOptions::new()
+                        .write(true)
+                        .open(&self.path)
+                        .and_then(|file| file.set_len(complete.len() as u64))
+                        .map_err(|e| self.error("repair", e))?;
+                }
+                parse(complete, &self.path)?
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(self.error("read", e)),
+        };
+        let entries: Vec<JournalEntry> = entries
+            .into_iter()
+            .filter(|entry| entry.sequence == 0 || entry.sequence > snapshot_sequence)
+            .collect();
+        self.len = entries.len();
+        self.sequence = entries
+            .iter()
+            .map(|entry| entry.sequence)
+            .fold(snapshot_sequence, u64::max);
+        Ok(entries)
+    }
+
+    // Numbers the entry as the next one.
+    pub fn append(&mut self, mut entry: JournalEntry) -> Result<(), TimerError> {
+        entry.sequence = self.sequence + 1;
+        let line = serde_json::to_string(&entry)
+            .map_err(|e| TimerError::new(&format!("Failed to serialize event: {}", e)))?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| self.error("open", e))?;
+        writeln!(file, "{}", line)
+            .and_then(|_| file.sync_data())
+            .map_err(|e| self.error("write", e))?;
+        self.len += 1;
+        self.sequence = entry.sequence;
+        Ok(())
+    }
+
+    // Called once the snapshot has all entries.
+    pub fn archive(&mut self) -> Result<(), TimerError> {
+        let Ok(content) = fs::read_to_string(&self.path) else {
+            return Ok(());
+        };
+        let content = complete_lines(&content);
+        if !content.is_empty() {
+            let mut history = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.history_path)
+                .map_err(|e| self.error("open", e))?;
+            history
+                .write_all(content.as_bytes())
+                .and_then(|_| history.sync_data())
+                .map_err(|e| self.error("write", e))?;
+        }
+        File::create(&self.path).map_err(|e| self.error("clear", e))?;
+        self.len = 0;
+        Ok(())
+    }
+
+    // Archived entries followed by the ones still in the journal. Entries archived twice,
+    // when compaction was cut short, are listed once.
+    pub fn history(&self) -> Result<Vec<JournalEntry>, TimerError> {
+        let mut entries = Vec::new();
+        let mut sequence = 0;
+        for path in [&self.history_path, &self.path] {
+            if let Ok(content) = fs::read_to_string(path) {
+                for entry in parse(complete_lines(&content), path)? {
+                    if entry.sequence == 0 || entry.sequence > sequence {
+                        sequence = sequence.max(entry.sequence);
+                        entries.push(entry);
+                    }
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    fn error(&self, action: &str, e: std::io::Error) -> TimerError {
+        TimerError::new(&format!(
+            "Failed to {} journal {}: {}",
+            action,
+            self.path.display(),
+            e
+        ))
+    }
+}
+
+// Content up to the last line break.
+fn complete_lines(content: &str) -> &str {
+    &content[..content.rfind('\n').map_or(0, |i| i + 1)]
+}
+
+// Entries written by older versions are migrated as they are read.
+fn parse(content: &str, path: &Path) -> Result<Vec<JournalEntry>, TimerError> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            let invalid = |e: &dyn std::fmt::Display| {
+                TimerError::new(&format!(
+                    "Invalid journal entry at {}:{}: {}",
+                    path.display(),
+                    index + 1,
+                    e
+                ))
+            };
+            let entry = serde_json::from_str(line).map_err(|e| invalid(&e))?;
+            let entry = migration::migrate_entry(entry).map_err(|e| invalid(&e.details))?;
+            serde_json::from_value(entry).map_err(|e| invalid(&e))
+        })
+        .collect()
+}
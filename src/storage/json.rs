@@ -0,0 +1,375 @@
+use std::{
+    fmt::Display,
+    fs::{self, File},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::SystemTime,
+};
+
+use serde_json::Value;
+
+use crate::{
+    clock::Clock,
+    error::TimerError,
+    model::{project::Project, timer::Timer},
+};
+
+use super::{
+    journal::{Event, Journal, JournalEntry},
+    migration::{self, DATA_VERSION},
+    overlaps, sibling, ProjectsData, Storage,
+};
+
+// Journal entries after which they are folded into the snapshot.
+const COMPACT_AFTER: usize = 200;
+
+// Modification time and size of the snapshot and the journal, to notice writes by others.
+type Fingerprint = [Option<(SystemTime, u64)>; 2];
+
+// A JSON snapshot of all data plus a journal of changes made since. Each change only appends
+// a line to the journal; loading replays the journal over the snapshot.
+pub struct JsonStorage {
+    path: PathBuf,
+    data: ProjectsData,
+    journal: Journal,
+    clock: Rc<dyn Clock>,
+    seen: Fingerprint,
+}
+
+impl JsonStorage {
+    pub fn new(path: &Path, clock: Rc<dyn Clock>) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            data: ProjectsData::default(),
+            journal: Journal::new(path),
+            clock,
+            seen: [None, None],
+        }
+    }
+
+    fn fingerprint(&self) -> Fingerprint {
+        [self.path.as_path(), self.journal.path()].map(|path| {
+            fs::metadata(path)
+                .ok()
+                .and_then(|metadata| Some((metadata.modified().ok()?, metadata.len())))
+        })
+    }
+
+    fn record(&mut self, event: Event) -> Result<(), TimerError> {
+        self.journal.append(JournalEntry {
+            sequence: 0,
+            version: DATA_VERSION,
+            time: self.clock.now(),
+            user: std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .ok(),
+            event: event.clone(),
+        })?;
+        event.apply(&mut self.data);
+        if self.journal.pending() >= COMPACT_AFTER {
+            self.compact()?;
+        }
+        self.seen = self.fingerprint();
+        Ok(())
+    }
+
+    fn compact(&mut self) -> Result<(), TimerError> {
+        self.data.journal_sequence = self.journal.sequence();
+        self.write_snapshot(&self.data)?;
+        self.journal.archive()
+    }
+
+    // The snapshot is replaced in one rename, so a crash leaves either the old one with the
+    // journal or the new one. Its content is on disk before the rename, and the rename before
+    // the journal is cleared.
+    fn write_snapshot(&self, data: &ProjectsData) -> Result<(), TimerError> {
+        let json = serde_json::to_string(data)
+            .map_err(|e| TimerError::new(&format!("Failed to serialize data: {}", e)))?;
+        let temp = sibling(&self.path, "tmp");
+        File::create(&temp)
+            .and_then(|mut file| {
+                file.write_all(json.as_bytes())?;
+                file.sync_all()
+            })
+            .and_then(|_| fs::rename(&temp, &self.path))
+            .and_then(|_| sync_dir(&self.path))
+            .map_err(|e| {
+                TimerError::new(&format!("Failed to write {}: {}", self.path.display(), e))
+            })
+    }
+
+    // A missing snapshot starts out empty. One of an older version is migrated and saved,
+    // after copying the original to `<data file>.v<version>.<timestamp>.bak`.
+    fn read_snapshot(&self) -> Result<ProjectsData, TimerError> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(ProjectsData::default()),
+            Err(e) => {
+                return Err(TimerError::new(&format!(
+                    "Failed to read {}: {}",
+                    self.path.display(),
+                    e
+                )))
+            }
+        };
+        let invalid = |e: &dyn Display| {
+            TimerError::new(&format!("Invalid data file {}: {}", self.path.display(), e))
+        };
+        let value: Value = serde_json::from_str(&content).map_err(|e| invalid(&e))?;
+        let version = migration::version(&value);
+        let value = migration::migrate(value)
+            .map_err(|e| TimerError::new(&format!("{}: {}", self.path.display(), e)))?;
+        let data = serde_json::from_value(value).map_err(|e| invalid(&e))?;
+
+        if version < DATA_VERSION {
+            let backup = sibling(
+                &self.path,
+                &format!("v{}.{}.bak", version, self.clock.now()),
+            );
+            if backup.exists() {
+                return Err(TimerError::new(&format!(
+                    "Backup {} already exists.",
+                    backup.display()
+                )));
+            }
+            fs::copy(&self.path, &backup).map_err(|e| {
+                TimerError::new(&format!("Failed to back up to {}: {}", backup.display(), e))
+            })?;
+            self.write_snapshot(&data)?;
+        }
+        Ok(data)
+    }
+}
+
+impl Storage for JsonStorage {
+    fn load(&mut self) -> Result<ProjectsData, TimerError> {
+        self.data = self.read_snapshot()?;
+        for entry in self.journal.read(self.data.journal_sequence)? {
+            entry.event.apply(&mut self.data);
+        }
+        self.seen = self.fingerprint();
+        Ok(self.data.clone())
+    }
+
+    fn save(&mut self, data: &ProjectsData) -> Result<(), TimerError> {
+        self.data = data.clone();
+        self.compact()?;
+        self.seen = self.fingerprint();
+        Ok(())
+    }
+
+    fn save_project(&mut self, project: &Project) -> Result<(), TimerError> {
+        let project = project.clone();
+        if self.data.projects.iter().any(|x| x.id == project.id) {
+            self.record(Event::ProjectEdited { project })
+        } else {
+            self.record(Event::ProjectCreated { project })
+        }
+    }
+
+    fn delete_project(&mut self, project_id: u64) -> Result<(), TimerError> {
+        self.record(Event::ProjectDeleted { project_id })
+    }
+
+    fn save_timer(&mut self, project_id: u64, timer: &Timer) -> Result<(), TimerError> {
+        let existing = self
+            .data
+            .projects
+            .iter()
+            .find(|x| x.id == project_id)
+            .ok_or_else(|| TimerError::new("Project with given id does not exists."))?
+            .timers
+            .iter()
+            .find(|x| x.id == timer.id);
+        let event = match (existing, timer.end_time) {
+            (None, _) => Event::TimerStarted {
+                project_id,
+                timer: timer.clone(),
+            },
+            (Some(existing), Some(end_time))
+                if existing.end_time.is_none() && existing.start_time == timer.start_time =>
+            {
+                Event::TimerStopped {
+                    project_id,
+                    timer_id: timer.id,
+                    end_time,
+                }
+            }
+            (Some(_), _) => Event::TimerEdited {
+                project_id,
+                timer: timer.clone(),
+            },
+        };
+        self.record(event)
+    }
+
+    fn save_invoice_counter(&mut self, invoice_counter: u32) -> Result<(), TimerError> {
+        self.record(Event::InvoiceCounterSet { invoice_counter })
+    }
+
+    fn timers_between(&self, from: u64, to: u64) -> Result<Vec<(u64, Timer)>, TimerError> {
+        Ok(self
+            .data
+            .projects
+            .iter()
+            .flat_map(|project| project.timers.iter().map(move |timer| (project.id, timer)))
+            .filter(|(_, timer)| overlaps(timer, from, to))
+            .map(|(project_id, timer)| (project_id, timer.clone()))
+            .collect())
+    }
+
+    fn changed(&self) -> Result<bool, TimerError> {
+        Ok(self.fingerprint() != self.seen)
+    }
+
+    fn history(&self) -> Result<Vec<JournalEntry>, TimerError> {
+        self.journal.history()
+    }
+}
+
+// Only Unix lets directories be opened to sync them.
+#[cfg(unix)]
+fn sync_dir(path: &Path) -> std::io::Result<()> {
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    File::open(dir)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_path: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        clock::FixedClock,
+        test_support::{timer, DataFile, NOW},
+    };
+
+    fn storage(file: &DataFile) -> JsonStorage {
+        let mut storage = JsonStorage::new(&file.0, Rc::new(FixedClock::new(NOW)));
+        storage.load().unwrap();
+        storage
+    }
+
+    fn journal_lines(file: &DataFile) -> Vec<String> {
+        fs::read_to_string(sibling(&file.0, "journal"))
+            .unwrap_or_default()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    fn start_and_stop(storage: &mut JsonStorage, timer_id: u32) {
+        let mut timer = timer(timer_id, NOW, None);
+        storage.save_timer(1, &timer).unwrap();
+        timer.end_time = Some(NOW + 60);
+        storage.save_timer(1, &timer).unwrap();
+    }
+
+    #[test]
+    fn changes_are_journaled_and_replayed() {
+        let file = DataFile::with_projects("json_journal", &[("Alpha", &[])]);
+        let snapshot = fs::read_to_string(&file.0).unwrap();
+        let mut storage = storage(&file);
+        start_and_stop(&mut storage, 1);
+
+        assert_eq!(fs::read_to_string(&file.0).unwrap(), snapshot);
+        let lines = journal_lines(&file);
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].contains(r#""event":"timer_started""#));
+        assert!(lines[1].contains(r#""event":"timer_stopped""#));
+
+        let data = JsonStorage::new(&file.0, Rc::new(FixedClock::new(NOW)))
+            .load()
+            .unwrap();
+        assert_eq!(data.projects[0].timers[0].end_time, Some(NOW + 60));
+    }
+
+    #[test]
+    fn line_cut_short_by_crash_is_dropped() {
+        let file = DataFile::with_projects("json_torn", &[("Alpha", &[])]);
+        start_and_stop(&mut storage(&file), 1);
+        let journal = sibling(&file.0, "journal");
+        let content = fs::read_to_string(&journal).unwrap();
+        fs::write(&journal, &content[..content.len() - 10]).unwrap();
+
+        let mut storage = storage(&file);
+        assert_eq!(storage.data.projects[0].timers[0].end_time, None);
+        storage.save_invoice_counter(1).unwrap();
+        assert_eq!(journal_lines(&file).len(), 2);
+        assert_eq!(self::storage(&file).data.invoice_counter, 1);
+    }
+
+    #[test]
+    fn entries_left_by_cut_short_compaction_are_not_replayed() {
+        let file = DataFile::with_projects("json_cut_short", &[("Alpha", &[])]);
+        let mut storage = storage(&file);
+        let mut project = storage.data.projects[0].clone();
+        project.name = "Beta".to_string();
+        storage.save_project(&project).unwrap();
+        let journal = fs::read_to_string(sibling(&file.0, "journal")).unwrap();
+
+        // The snapshot is written, then the journal is left as it was.
+        let mut data = storage.data.clone();
+        data.projects[0].timers.push(timer(1, NOW, Some(NOW + 60)));
+        storage.save(&data).unwrap();
+        fs::write(sibling(&file.0, "journal"), &journal).unwrap();
+
+        let mut storage = self::storage(&file);
+        assert_eq!(storage.data.projects[0].timers.len(), 1);
+        storage.save_invoice_counter(1).unwrap();
+        assert_eq!(self::storage(&file).data.invoice_counter, 1);
+        storage.compact().unwrap();
+        assert_eq!(storage.history().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn old_snapshot_is_backed_up_and_migrated() {
+        let file = DataFile::new("json_migrate");
+        let original = r#"{"projects":[{"id":1,"name":"Alpha","timers":[]}]}"#;
+        fs::write(&file.0, original).unwrap();
+        // Written before entries had a version.
+        let entry = r#"{"time":1,"event":"invoice_counter_set","invoice_counter":4}"#;
+        fs::write(sibling(&file.0, "journal"), format!("{}\n", entry)).unwrap();
+
+        let storage = storage(&file);
+        assert_eq!(storage.data.projects[0].name, "Alpha");
+        assert_eq!(storage.data.invoice_counter, 4);
+        let backup = sibling(&file.0, &format!("v1.{}.bak", NOW));
+        assert_eq!(fs::read_to_string(backup).unwrap(), original);
+        let snapshot: Value = serde_json::from_str(&fs::read_to_string(&file.0).unwrap()).unwrap();
+        assert_eq!(snapshot["version"], DATA_VERSION);
+    }
+
+    #[test]
+    fn newer_or_invalid_snapshot_is_refused() {
+        let file = DataFile::new("json_refused");
+        for content in [r#"{"version":99,"projects":[]}"#, r#"{"projects":"#] {
+            fs::write(&file.0, content).unwrap();
+            let mut storage = JsonStorage::new(&file.0, Rc::new(FixedClock::new(NOW)));
+            assert!(storage.load().is_err());
+            assert_eq!(fs::read_to_string(&file.0).unwrap(), content);
+        }
+    }
+
+    #[test]
+    fn journal_is_compacted_into_snapshot_and_history() {
+        let file = DataFile::with_projects("json_compact", &[("Alpha", &[])]);
+        let mut storage = storage(&file);
+        for timer_id in 1..=COMPACT_AFTER as u32 / 2 {
+            start_and_stop(&mut storage, timer_id);
+        }
+
+        assert!(journal_lines(&file).is_empty());
+        let snapshot: ProjectsData =
+            serde_json::from_str(&fs::read_to_string(&file.0).unwrap()).unwrap();
+        assert_eq!(snapshot.projects[0].timers.len(), COMPACT_AFTER / 2);
+        assert_eq!(storage.history().unwrap().len(), COMPACT_AFTER);
+    }
+}
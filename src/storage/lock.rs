@@ -0,0 +1,90 @@
+use std::{
+    fs::{File, OpenOptions, TryLockError},
+    io::{Read, Seek, Write},
+    path::Path,
+};
+
+use crate::error::TimerError;
+
+use super::sibling;
+
+// Advisory lock on `<data file>.lock`, held while a change is written, so other timers-rs
+// processes wait instead of writing at the same time. Released when dropped.
+pub struct WriteLock {
+    _file: File,
+}
+
+impl WriteLock {
+    pub fn acquire(data_file: &Path) -> Result<Self, TimerError> {
+        let path = sibling(data_file, "lock");
+        let file = open(&path)?;
+        file.lock()
+            .map_err(|e| TimerError::new(&format!("Failed to lock {}: {}", path.display(), e)))?;
+        Ok(Self { _file: file })
+    }
+}
+
+// Lock on `<data file>.instance` held by the TUI while it runs, so only one is open on a data
+// file. The file has the process id of the holder, for the error shown to the next one.
+pub struct InstanceLock {
+    _file: File,
+}
+
+impl InstanceLock {
+    pub fn acquire(data_file: &Path) -> Result<Self, TimerError> {
+        let path = sibling(data_file, "instance");
+        let mut file = open(&path)?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                let mut pid = String::new();
+                let _ = file.read_to_string(&mut pid);
+                return Err(TimerError::new(&format!(
+                    "{} is already open in another timers-rs (process {}).",
+                    data_file.display(),
+                    pid.trim()
+                )));
+            }
+            Err(TryLockError::Error(e)) => {
+                return Err(TimerError::new(&format!(
+                    "Failed to lock {}: {}",
+                    path.display(),
+                    e
+                )))
+            }
+        }
+        file.set_len(0)
+            .and_then(|_| file.rewind())
+            .and_then(|_| write!(file, "{}", std::process::id()))
+            .map_err(|e| TimerError::new(&format!("Failed to write {}: {}", path.display(), e)))?;
+        Ok(Self { _file: file })
+    }
+}
+
+fn open(path: &Path) -> Result<File, TimerError> {
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .map_err(|e| TimerError::new(&format!("Failed to open {}: {}", path.display(), e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::DataFile;
+
+    #[test]
+    fn second_instance_is_refused_until_first_ends() {
+        let file = DataFile::new("instance_lock");
+        let first = InstanceLock::acquire(&file.0).unwrap();
+
+        let error = InstanceLock::acquire(&file.0).err().unwrap();
+        assert!(error.details.contains(&std::process::id().to_string()));
+
+        drop(first);
+        assert!(InstanceLock::acquire(&file.0).is_ok());
+    }
+}
@@ -0,0 +1,107 @@
+use serde_json::{json, Map, Value};
+
+use crate::error::TimerError;
+
+// Version of the data written by this build. Files without a version field are version 1.
+pub const DATA_VERSION: u32 = 2;
+
+// `MIGRATIONS[i]` turns version `i + 1` into version `i + 2`.
+const MIGRATIONS: [fn(&mut Map<String, Value>); DATA_VERSION as usize - 1] = [v1_to_v2];
+
+// The journal came with version 2, so its entries without a version field are version 2.
+const FIRST_JOURNAL_VERSION: u32 = 2;
+
+// `ENTRY_MIGRATIONS[i]` turns a journal entry of version `i + 2` into version `i + 3`. Entries
+// carry projects and timers, so a change to those needs a migration here as well.
+const ENTRY_MIGRATIONS: [fn(&mut Map<String, Value>);
+    (DATA_VERSION - FIRST_JOURNAL_VERSION) as usize] = [];
+
+pub fn version(data: &Value) -> u32 {
+    version_or(data, 1)
+}
+
+fn version_or(data: &Value, default: u32) -> u32 {
+    data.get("version")
+        .and_then(Value::as_u64)
+        .map_or(default, |version| {
+            u32::try_from(version).unwrap_or(u32::MAX).max(1)
+        })
+}
+
+// Brings data of an older version up to `DATA_VERSION`; data of a newer one can't be read.
+pub fn migrate(data: Value) -> Result<Value, TimerError> {
+    let from = version(&data);
+    run(data, from, 1, &MIGRATIONS)
+}
+
+// Like `migrate`, for a single journal entry.
+pub fn migrate_entry(entry: Value) -> Result<Value, TimerError> {
+    let from = version_or(&entry, FIRST_JOURNAL_VERSION).max(FIRST_JOURNAL_VERSION);
+    run(entry, from, FIRST_JOURNAL_VERSION, &ENTRY_MIGRATIONS)
+}
+
+// `migrations[i]` turns version `first + i` into `first + i + 1`.
+fn run(
+    mut data: Value,
+    from: u32,
+    first: u32,
+    migrations: &[fn(&mut Map<String, Value>)],
+) -> Result<Value, TimerError> {
+    if from > DATA_VERSION {
+        return Err(TimerError::new(&format!(
+            "Data version {} is newer than {}, the latest this timers-rs can read. Update timers-rs to open it.",
+            from, DATA_VERSION
+        )));
+    }
+    let Some(object) = data.as_object_mut() else {
+        return Err(TimerError::new("Data is not a JSON object."));
+    };
+    for migration in migrations.iter().skip((from - first) as usize) {
+        migration(object);
+    }
+    object.insert("version".to_string(), json!(DATA_VERSION));
+    Ok(data)
+}
+
+// Before versions, `favorites` and `invoice_counter` could be missing.
+fn v1_to_v2(data: &mut Map<String, Value>) {
+    data.entry("favorites").or_insert(json!([]));
+    data.entry("invoice_counter").or_insert(json!(0));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unversioned_data_is_migrated() {
+        let data = migrate(json!({ "projects": [] })).unwrap();
+        assert_eq!(
+            data,
+            json!({ "projects": [], "favorites": [], "invoice_counter": 0, "version": 2 })
+        );
+    }
+
+    #[test]
+    fn current_data_is_unchanged() {
+        let data = json!({ "projects": [], "favorites": [1], "invoice_counter": 3, "version": 2 });
+        assert_eq!(migrate(data.clone()).unwrap(), data);
+    }
+
+    #[test]
+    fn journal_entries_are_brought_to_current_version() {
+        let entry = json!({ "time": 1, "event": "invoice_counter_set", "invoice_counter": 2 });
+        let migrated = migrate_entry(entry.clone()).unwrap();
+        assert_eq!(migrated["version"], DATA_VERSION);
+        assert_eq!(migrated["invoice_counter"], entry["invoice_counter"]);
+
+        let newer = json!({ "version": DATA_VERSION + 1, "time": 1 });
+        assert!(migrate_entry(newer).unwrap_err().details.contains("newer"));
+    }
+
+    #[test]
+    fn newer_data_is_refused() {
+        let error = migrate(json!({ "projects": [], "version": DATA_VERSION + 1 })).unwrap_err();
+        assert!(error.details.contains("newer"));
+    }
+}
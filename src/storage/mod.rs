@@ -0,0 +1,196 @@
+pub mod journal;
+pub mod json;
+pub mod lock;
+pub mod migration;
+pub mod sqlite;
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    clock::Clock,
+    error::TimerError,
+    model::{project::Project, timer::Timer},
+};
+
+use journal::JournalEntry;
+use migration::DATA_VERSION;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectsData {
+    pub version: u32,
+    pub projects: Vec<Project>,
+    pub favorites: Vec<u64>,
+    pub invoice_counter: u32,
+    // Sequence number of the last journal entry in a JSON snapshot.
+    #[serde(default)]
+    pub journal_sequence: u64,
+}
+
+impl Default for ProjectsData {
+    fn default() -> Self {
+        Self {
+            version: DATA_VERSION,
+            projects: Vec::new(),
+            favorites: Vec::new(),
+            invoice_counter: 0,
+            journal_sequence: 0,
+        }
+    }
+}
+
+// Where projects and timers are kept. Besides loading and saving everything at once, single
+// changes can be written, so backends that support it don't rewrite all data on each change.
+pub trait Storage {
+    fn load(&mut self) -> Result<ProjectsData, TimerError>;
+
+    fn save(&mut self, data: &ProjectsData) -> Result<(), TimerError>;
+
+    // Adds the project or replaces the one with the same id, timers included.
+    fn save_project(&mut self, project: &Project) -> Result<(), TimerError>;
+
+    fn delete_project(&mut self, project_id: u64) -> Result<(), TimerError>;
+
+    // Appends the timer to the project or replaces the one with the same id.
+    fn save_timer(&mut self, project_id: u64, timer: &Timer) -> Result<(), TimerError>;
+
+    fn save_invoice_counter(&mut self, invoice_counter: u32) -> Result<(), TimerError>;
+
+    // Timers overlapping `from..to` as `(project id, timer)`; running timers are open ended.
+    fn timers_between(&self, from: u64, to: u64) -> Result<Vec<(u64, Timer)>, TimerError>;
+
+    // Whether another program wrote the data since it was last loaded or written here.
+    fn changed(&self) -> Result<bool, TimerError>;
+
+    // All recorded changes, oldest first.
+    fn history(&self) -> Result<Vec<JournalEntry>, TimerError> {
+        Err(TimerError::new("This storage keeps no history of changes."))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageKind {
+    #[default]
+    Json,
+    Sqlite,
+}
+
+impl fmt::Display for StorageKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageKind::Json => write!(f, "json"),
+            StorageKind::Sqlite => write!(f, "sqlite"),
+        }
+    }
+}
+
+pub fn open(
+    kind: StorageKind,
+    path: &Path,
+    clock: Rc<dyn Clock>,
+) -> Result<Box<dyn Storage>, TimerError> {
+    Ok(match kind {
+        StorageKind::Json => Box::new(json::JsonStorage::new(path, clock)),
+        StorageKind::Sqlite => Box::new(sqlite::SqliteStorage::open(path)?),
+    })
+}
+
+// `<path>.<extension>`, next to the data file.
+fn sibling(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".");
+    name.push(extension);
+    PathBuf::from(name)
+}
+
+fn overlaps(timer: &Timer, from: u64, to: u64) -> bool {
+    timer.start_time < to && timer.end_time.is_none_or(|end| end >= from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        clock::FixedClock,
+        test_support::{project, timer, DataFile, NOW},
+    };
+
+    fn spans(data: &ProjectsData) -> Vec<(u64, u32, u64, Option<u64>)> {
+        data.projects
+            .iter()
+            .flat_map(|project| {
+                project
+                    .timers
+                    .iter()
+                    .map(|timer| (project.id, timer.id, timer.start_time, timer.end_time))
+            })
+            .collect()
+    }
+
+    // Runs the same changes on a backend, then checks a freshly opened one sees them.
+    fn check_backend(kind: StorageKind, name: &str) {
+        let file = DataFile::new(name);
+        let mut storage = open(kind, &file.0, Rc::new(FixedClock::new(NOW))).unwrap();
+        assert!(storage.load().unwrap().projects.is_empty());
+
+        storage
+            .save(&ProjectsData {
+                projects: vec![
+                    project(1, "Alpha", vec![timer(1, NOW - 7200, Some(NOW - 3600))]),
+                    project(2, "Beta", Vec::new()),
+                ],
+                favorites: vec![2],
+                invoice_counter: 3,
+                ..ProjectsData::default()
+            })
+            .unwrap();
+        storage.save_timer(2, &timer(1, NOW, None)).unwrap();
+        storage
+            .save_timer(2, &timer(1, NOW, Some(NOW + 60)))
+            .unwrap();
+        storage
+            .save_project(&project(3, "Gamma", vec![timer(1, NOW + 600, None)]))
+            .unwrap();
+        storage.delete_project(1).unwrap();
+        storage.save_invoice_counter(4).unwrap();
+
+        let data = open(kind, &file.0, Rc::new(FixedClock::new(NOW)))
+            .unwrap()
+            .load()
+            .unwrap();
+        let names: Vec<&str> = data.projects.iter().map(|x| x.name.as_str()).collect();
+        assert_eq!(names, ["Beta", "Gamma"]);
+        assert_eq!(
+            spans(&data),
+            [(2, 1, NOW, Some(NOW + 60)), (3, 1, NOW + 600, None)]
+        );
+        assert_eq!(data.favorites, [2]);
+        assert_eq!(data.invoice_counter, 4);
+
+        let between: Vec<u64> = storage
+            .timers_between(NOW + 300, NOW + 900)
+            .unwrap()
+            .iter()
+            .map(|(project_id, _)| *project_id)
+            .collect();
+        assert_eq!(between, [3]);
+        assert_eq!(storage.timers_between(NOW + 60, NOW + 61).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn json_storage_keeps_changes() {
+        check_backend(StorageKind::Json, "storage_json");
+    }
+
+    #[test]
+    fn sqlite_storage_keeps_changes() {
+        check_backend(StorageKind::Sqlite, "storage_sqlite");
+    }
+}
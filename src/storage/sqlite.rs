@@ -0,0 +1,319 @@
+use std::path::Path;
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    error::TimerError,
+    model::{billing::Billing, project::Project, timer::Timer},
+};
+
+use super::{ProjectsData, Storage};
+
+// Kept in `PRAGMA user_version`; a new database starts at 0.
+const SCHEMA_VERSION: u32 = 1;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS projects (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL,
+        billing TEXT,
+        rounding TEXT
+    );
+    CREATE TABLE IF NOT EXISTS timers (
+        project_id INTEGER NOT NULL,
+        id INTEGER NOT NULL,
+        start_time INTEGER NOT NULL,
+        end_time INTEGER,
+        note TEXT,
+        billing TEXT,
+        PRIMARY KEY (project_id, id)
+    );
+    CREATE INDEX IF NOT EXISTS timers_start_time ON timers (start_time);
+    CREATE TABLE IF NOT EXISTS favorites (
+        project_id INTEGER PRIMARY KEY
+    );
+    CREATE TABLE IF NOT EXISTS settings (
+        key TEXT PRIMARY KEY,
+        value INTEGER NOT NULL
+    );
+";
+
+// Timers are rows, so starting or stopping one only writes that row. Billing and rounding
+// are kept as JSON, the same way the JSON file has them.
+pub struct SqliteStorage {
+    connection: Connection,
+    // `PRAGMA data_version` when last loaded; it changes when another connection commits.
+    seen_version: i64,
+}
+
+impl SqliteStorage {
+    pub fn open(path: &Path) -> Result<Self, TimerError> {
+        let failed = |e: rusqlite::Error| {
+            TimerError::new(&format!(
+                "Failed to open database {}: {}",
+                path.display(),
+                e
+            ))
+        };
+        let connection = Connection::open(path).map_err(failed)?;
+        let version: u32 = connection
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .map_err(failed)?;
+        if version > SCHEMA_VERSION {
+            return Err(TimerError::new(&format!(
+                "Database {} has schema version {}, newer than {}, the latest this timers-rs can read. Update timers-rs to open it.",
+                path.display(),
+                version,
+                SCHEMA_VERSION
+            )));
+        }
+        connection
+            .execute_batch(SCHEMA)
+            .and_then(|_| connection.pragma_update(None, "user_version", SCHEMA_VERSION))
+            .map_err(failed)?;
+        Ok(Self {
+            connection,
+            seen_version: 0,
+        })
+    }
+
+    fn data_version(&self) -> rusqlite::Result<i64> {
+        self.connection
+            .pragma_query_value(None, "data_version", |row| row.get(0))
+    }
+
+    fn load_projects(&self) -> rusqlite::Result<Vec<Project>> {
+        let mut projects = self
+            .connection
+            .prepare("SELECT id, name, billing, rounding FROM projects ORDER BY id")?
+            .query_map([], |row| {
+                Ok(Project {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    timers: Vec::new(),
+                    billing: from_json(row, 2)?.unwrap_or_default(),
+                    rounding: from_json(row, 3)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<Project>>>()?;
+
+        let mut statement = self.connection.prepare(
+            "SELECT project_id, id, start_time, end_time, note, billing FROM timers
+             ORDER BY project_id, id",
+        )?;
+        for timer in statement.query_map([], project_timer)? {
+            let (project_id, timer) = timer?;
+            if let Some(project) = projects.iter_mut().find(|x| x.id == project_id) {
+                project.timers.push(timer);
+            }
+        }
+        Ok(projects)
+    }
+
+    fn load_data(&self) -> rusqlite::Result<ProjectsData> {
+        let favorites = self
+            .connection
+            .prepare("SELECT project_id FROM favorites ORDER BY project_id")?
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<u64>>>()?;
+        let invoice_counter = self
+            .connection
+            .query_row(
+                "SELECT value FROM settings WHERE key = 'invoice_counter'",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?
+            .unwrap_or(0);
+        Ok(ProjectsData {
+            projects: self.load_projects()?,
+            favorites,
+            invoice_counter,
+            ..ProjectsData::default()
+        })
+    }
+
+    fn save_data(&mut self, data: &ProjectsData) -> rusqlite::Result<()> {
+        let transaction = self.connection.transaction()?;
+        transaction.execute_batch(
+            "DELETE FROM timers; DELETE FROM projects; DELETE FROM favorites; DELETE FROM settings;",
+        )?;
+        for project in &data.projects {
+            insert_project(&transaction, project)?;
+        }
+        for project_id in &data.favorites {
+            transaction.execute(
+                "INSERT INTO favorites (project_id) VALUES (?1)",
+                [project_id],
+            )?;
+        }
+        write_invoice_counter(&transaction, data.invoice_counter)?;
+        transaction.commit()
+    }
+
+    fn replace_project(&mut self, project: &Project) -> rusqlite::Result<()> {
+        let transaction = self.connection.transaction()?;
+        delete_project(&transaction, project.id)?;
+        insert_project(&transaction, project)?;
+        transaction.commit()
+    }
+
+    fn remove_project(&mut self, project_id: u64) -> rusqlite::Result<()> {
+        let transaction = self.connection.transaction()?;
+        delete_project(&transaction, project_id)?;
+        transaction.execute("DELETE FROM favorites WHERE project_id = ?1", [project_id])?;
+        transaction.commit()
+    }
+
+    fn load_timers_between(&self, from: u64, to: u64) -> rusqlite::Result<Vec<(u64, Timer)>> {
+        // SQLite integers are signed.
+        let (from, to) = (from.min(i64::MAX as u64), to.min(i64::MAX as u64));
+        self.connection
+            .prepare(
+                "SELECT project_id, id, start_time, end_time, note, billing FROM timers
+                 WHERE start_time < ?2 AND (end_time IS NULL OR end_time >= ?1)
+                 ORDER BY start_time",
+            )?
+            .query_map(params![from, to], project_timer)?
+            .collect()
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn load(&mut self) -> Result<ProjectsData, TimerError> {
+        self.seen_version = self.data_version().map_err(error)?;
+        self.load_data().map_err(error)
+    }
+
+    fn save(&mut self, data: &ProjectsData) -> Result<(), TimerError> {
+        self.save_data(data).map_err(error)
+    }
+
+    fn save_project(&mut self, project: &Project) -> Result<(), TimerError> {
+        self.replace_project(project).map_err(error)
+    }
+
+    fn delete_project(&mut self, project_id: u64) -> Result<(), TimerError> {
+        self.remove_project(project_id).map_err(error)
+    }
+
+    fn save_timer(&mut self, project_id: u64, timer: &Timer) -> Result<(), TimerError> {
+        insert_timer(&self.connection, project_id, timer).map_err(error)
+    }
+
+    fn save_invoice_counter(&mut self, invoice_counter: u32) -> Result<(), TimerError> {
+        write_invoice_counter(&self.connection, invoice_counter).map_err(error)
+    }
+
+    fn timers_between(&self, from: u64, to: u64) -> Result<Vec<(u64, Timer)>, TimerError> {
+        self.load_timers_between(from, to).map_err(error)
+    }
+
+    fn changed(&self) -> Result<bool, TimerError> {
+        Ok(self.data_version().map_err(error)? != self.seen_version)
+    }
+}
+
+fn insert_project(connection: &Connection, project: &Project) -> rusqlite::Result<()> {
+    connection.execute(
+        "INSERT INTO projects (id, name, billing, rounding) VALUES (?1, ?2, ?3, ?4)",
+        params![
+            project.id,
+            project.name,
+            billing_json(&project.billing)?,
+            project.rounding.as_ref().map(to_json).transpose()?
+        ],
+    )?;
+    for timer in &project.timers {
+        insert_timer(connection, project.id, timer)?;
+    }
+    Ok(())
+}
+
+fn delete_project(connection: &Connection, project_id: u64) -> rusqlite::Result<()> {
+    connection.execute("DELETE FROM timers WHERE project_id = ?1", [project_id])?;
+    connection.execute("DELETE FROM projects WHERE id = ?1", [project_id])?;
+    Ok(())
+}
+
+fn insert_timer(connection: &Connection, project_id: u64, timer: &Timer) -> rusqlite::Result<()> {
+    connection.execute(
+        "INSERT OR REPLACE INTO timers (project_id, id, start_time, end_time, note, billing)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            project_id,
+            timer.id,
+            timer.start_time,
+            timer.end_time,
+            timer.note,
+            billing_json(&timer.billing)?
+        ],
+    )?;
+    Ok(())
+}
+
+fn write_invoice_counter(connection: &Connection, invoice_counter: u32) -> rusqlite::Result<()> {
+    connection.execute(
+        "INSERT OR REPLACE INTO settings (key, value) VALUES ('invoice_counter', ?1)",
+        [invoice_counter],
+    )?;
+    Ok(())
+}
+
+fn project_timer(row: &Row) -> rusqlite::Result<(u64, Timer)> {
+    Ok((
+        row.get(0)?,
+        Timer {
+            id: row.get(1)?,
+            start_time: row.get(2)?,
+            end_time: row.get(3)?,
+            note: row.get(4)?,
+            billing: from_json(row, 5)?.unwrap_or_default(),
+        },
+    ))
+}
+
+fn billing_json(billing: &Billing) -> rusqlite::Result<Option<String>> {
+    (!billing.is_empty()).then(|| to_json(billing)).transpose()
+}
+
+fn to_json<T: Serialize>(value: &T) -> rusqlite::Result<String> {
+    serde_json::to_string(value).map_err(|e| rusqlite::Error::ToSqlConversionFailure(e.into()))
+}
+
+fn from_json<T: DeserializeOwned>(row: &Row, index: usize) -> rusqlite::Result<Option<T>> {
+    row.get::<_, Option<String>>(index)?
+        .map(|json| {
+            serde_json::from_str(&json).map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(
+                    index,
+                    rusqlite::types::Type::Text,
+                    e.into(),
+                )
+            })
+        })
+        .transpose()
+}
+
+fn error(e: rusqlite::Error) -> TimerError {
+    TimerError::new(&format!("Database error: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::DataFile;
+
+    #[test]
+    fn newer_schema_is_refused() {
+        let file = DataFile::new("sqlite_version");
+        SqliteStorage::open(&file.0).unwrap();
+        Connection::open(&file.0)
+            .unwrap()
+            .pragma_update(None, "user_version", SCHEMA_VERSION + 1)
+            .unwrap();
+
+        assert!(SqliteStorage::open(&file.0).is_err());
+    }
+}
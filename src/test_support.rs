@@ -0,0 +1,100 @@
+use std::path::PathBuf;
+
+use serde_json::{json, Value};
+
+use crate::{
+    model::{billing::Billing, project::Project, timer::Timer},
+    storage::migration::DATA_VERSION,
+};
+
+// Friday 2024-03-15 12:00:00 UTC.
+pub const NOW: u64 = 1710504000;
+
+// Start and end of a timer.
+pub type TimerSpan = (u64, Option<u64>);
+
+// Data file in the temp directory, removed when the test ends.
+pub struct DataFile(pub PathBuf);
+
+impl DataFile {
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "timers-rs-test-{}-{}.json",
+            std::process::id(),
+            name
+        ));
+        let file = Self(path);
+        file.remove();
+        file
+    }
+
+    // `projects` are `(name, timers)` with timers as `(start, end)`; ids are given in order.
+    pub fn with_projects(name: &str, projects: &[(&str, &[TimerSpan])]) -> Self {
+        let file = Self::new(name);
+        let projects: Vec<Value> = projects
+            .iter()
+            .enumerate()
+            .map(|(i, (name, timers))| {
+                let timers: Vec<Value> = timers
+                    .iter()
+                    .enumerate()
+                    .map(|(j, (start, end))| {
+                        json!({ "id": j + 1, "start_time": start, "end_time": end })
+                    })
+                    .collect();
+                json!({ "id": i + 1, "name": name, "timers": timers })
+            })
+            .collect();
+        let data = json!({
+            "version": DATA_VERSION,
+            "projects": projects,
+            "favorites": [],
+            "invoice_counter": 0
+        });
+        std::fs::write(&file.0, data.to_string()).expect("Failed to write data file");
+        file
+    }
+
+    pub fn path(&self) -> String {
+        self.0.to_string_lossy().to_string()
+    }
+
+    // Along with the journal, history, backup and lock files next to it.
+    fn remove(&self) {
+        let _ = std::fs::remove_file(&self.0);
+        for extension in [
+            "journal",
+            "history",
+            &format!("v1.{}.bak", NOW),
+            "lock",
+            "instance",
+        ] {
+            let mut path = self.0.clone().into_os_string();
+            path.push(format!(".{}", extension));
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+impl Drop for DataFile {
+    fn drop(&mut self) {
+        self.remove();
+    }
+}
+
+pub fn timer(id: u32, start_time: u64, end_time: Option<u64>) -> Timer {
+    Timer {
+        id,
+        start_time,
+        end_time,
+        note: None,
+        billing: Billing::default(),
+    }
+}
+
+pub fn project(id: u64, name: &str, timers: Vec<Timer>) -> Project {
+    Project {
+        timers,
+        ..Project::new(id, name.to_string())
+    }
+}
@@ -0,0 +1,373 @@
+use std::{collections::BTreeMap, ffi::OsStr, str::FromStr};
+
+use ratatui::style::{Color, Modifier, Style};
+use serde::{Deserialize, Deserializer};
+
+use crate::error::TimerError;
+
+pub const DEFAULT_THEME: &str = "dark";
+pub const BUILT_IN_THEMES: [&str; 4] = ["dark", "light", "high-contrast", "monochrome"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub border: Style,
+    pub focused_border: Style,
+    pub header: Style,
+    pub button: Style,
+    pub selected_button: Style,
+    pub clock: Style,
+    pub timestamp: Style,
+    pub running_timer: Style,
+    pub duration: Style,
+    pub editing: Style,
+    pub highlight: Style,
+    pub key: Style,
+    pub title: Style,
+    pub error: Style,
+    pub message: Style,
+    pub popup: Style,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemeSlot {
+    Border,
+    FocusedBorder,
+    Header,
+    Button,
+    SelectedButton,
+    Clock,
+    Timestamp,
+    RunningTimer,
+    Duration,
+    Editing,
+    Highlight,
+    Key,
+    Title,
+    Error,
+    Message,
+    Popup,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        Self {
+            border: Style::default(),
+            focused_border: Style::default().fg(Color::Magenta),
+            header: bold.fg(Color::Green),
+            button: Style::default().fg(Color::Green),
+            selected_button: bold.fg(Color::Yellow).add_modifier(Modifier::UNDERLINED),
+            clock: bold.fg(Color::Red),
+            timestamp: Style::default().fg(Color::Green),
+            running_timer: bold.fg(Color::Yellow),
+            duration: bold.fg(Color::Red),
+            editing: Style::default().fg(Color::Yellow),
+            highlight: bold,
+            key: bold,
+            title: bold.fg(Color::Magenta),
+            error: Style::default().fg(Color::Red),
+            message: Style::default().fg(Color::Green),
+            popup: Style::default().bg(Color::Black),
+        }
+    }
+
+    pub fn light() -> Self {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        Self {
+            border: Style::default().fg(Color::DarkGray),
+            focused_border: Style::default().fg(Color::Blue),
+            header: bold.fg(Color::Blue),
+            button: Style::default().fg(Color::Blue),
+            selected_button: bold.fg(Color::Magenta).add_modifier(Modifier::UNDERLINED),
+            clock: bold.fg(Color::Red),
+            timestamp: Style::default().fg(Color::Black),
+            running_timer: bold.fg(Color::Magenta),
+            duration: bold.fg(Color::Red),
+            editing: Style::default().fg(Color::Magenta),
+            highlight: bold,
+            key: bold,
+            title: bold.fg(Color::Blue),
+            error: bold.fg(Color::Red),
+            message: Style::default().fg(Color::Green),
+            popup: Style::default().fg(Color::Black).bg(Color::White),
+        }
+    }
+
+    pub fn high_contrast() -> Self {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        Self {
+            border: Style::default().fg(Color::White),
+            focused_border: bold.fg(Color::LightYellow),
+            header: bold.fg(Color::White),
+            button: Style::default().fg(Color::White),
+            selected_button: bold.fg(Color::Black).bg(Color::LightYellow),
+            clock: bold.fg(Color::LightYellow),
+            timestamp: Style::default().fg(Color::White),
+            running_timer: bold.fg(Color::LightGreen),
+            duration: bold.fg(Color::LightCyan),
+            editing: bold
+                .fg(Color::LightYellow)
+                .add_modifier(Modifier::UNDERLINED),
+            highlight: bold.fg(Color::Black).bg(Color::White),
+            key: bold.fg(Color::LightYellow),
+            title: bold.fg(Color::White).add_modifier(Modifier::UNDERLINED),
+            error: bold.fg(Color::White).bg(Color::Red),
+            message: bold.fg(Color::Black).bg(Color::LightGreen),
+            popup: Style::default().fg(Color::White).bg(Color::Black),
+        }
+    }
+
+    // Only modifiers, no colours; used whenever NO_COLOR is set.
+    pub fn monochrome() -> Self {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        Self {
+            border: Style::default(),
+            focused_border: bold,
+            header: bold,
+            button: Style::default(),
+            selected_button: Style::default().add_modifier(Modifier::REVERSED),
+            clock: bold,
+            timestamp: Style::default(),
+            running_timer: Style::default().add_modifier(Modifier::ITALIC),
+            duration: bold,
+            editing: Style::default().add_modifier(Modifier::UNDERLINED),
+            highlight: Style::default().add_modifier(Modifier::REVERSED),
+            key: bold,
+            title: bold,
+            error: bold.add_modifier(Modifier::REVERSED),
+            message: Style::default(),
+            popup: Style::default(),
+        }
+    }
+
+    pub fn built_in(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self::dark()),
+            "light" => Some(Self::light()),
+            "high-contrast" => Some(Self::high_contrast()),
+            "monochrome" => Some(Self::monochrome()),
+            _ => None,
+        }
+    }
+
+    // Custom themes start from a built-in (or another custom) theme and change single slots.
+    pub fn resolve(name: &str, themes: &BTreeMap<String, ThemeConfig>) -> Result<Self, TimerError> {
+        let mut visited = Vec::new();
+        let mut current = name;
+        let mut overrides = Vec::new();
+        // A custom theme may share the name of the built-in one it changes, like
+        // `[themes.dark]`, so visited names fall back to built-in themes.
+        let mut theme = loop {
+            match themes.get(current) {
+                Some(config) if !visited.contains(&current) => {
+                    visited.push(current);
+                    overrides.push(&config.styles);
+                    current = config.base.as_deref().unwrap_or(DEFAULT_THEME);
+                }
+                _ => match Self::built_in(current) {
+                    Some(theme) => break theme,
+                    None if visited.contains(&current) => {
+                        return Err(TimerError::new(&format!(
+                            "Theme {} is based on itself.",
+                            current
+                        )))
+                    }
+                    None => {
+                        return Err(TimerError::new(&format!(
+                            "Unknown theme: {}. Expected one of {} or a theme from [themes].",
+                            current,
+                            BUILT_IN_THEMES.join(", ")
+                        )))
+                    }
+                },
+            }
+        };
+
+        for styles in overrides.into_iter().rev() {
+            for (slot, style) in styles {
+                theme.set(*slot, style.0);
+            }
+        }
+        Ok(theme)
+    }
+
+    // NO_COLOR (https://no-color.org) wins over the configured theme when it is set and
+    // not empty.
+    pub fn select(
+        name: &str,
+        themes: &BTreeMap<String, ThemeConfig>,
+        no_color: Option<&OsStr>,
+    ) -> Self {
+        if no_color.is_some_and(|value| !value.is_empty()) {
+            return Self::monochrome();
+        }
+        Self::resolve(name, themes).unwrap_or_default()
+    }
+
+    pub fn set(&mut self, slot: ThemeSlot, style: Style) {
+        let target = match slot {
+            ThemeSlot::Border => &mut self.border,
+            ThemeSlot::FocusedBorder => &mut self.focused_border,
+            ThemeSlot::Header => &mut self.header,
+            ThemeSlot::Button => &mut self.button,
+            ThemeSlot::SelectedButton => &mut self.selected_button,
+            ThemeSlot::Clock => &mut self.clock,
+            ThemeSlot::Timestamp => &mut self.timestamp,
+            ThemeSlot::RunningTimer => &mut self.running_timer,
+            ThemeSlot::Duration => &mut self.duration,
+            ThemeSlot::Editing => &mut self.editing,
+            ThemeSlot::Highlight => &mut self.highlight,
+            ThemeSlot::Key => &mut self.key,
+            ThemeSlot::Title => &mut self.title,
+            ThemeSlot::Error => &mut self.error,
+            ThemeSlot::Message => &mut self.message,
+            ThemeSlot::Popup => &mut self.popup,
+        };
+        *target = style;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ThemeConfig {
+    pub base: Option<String>,
+    #[serde(flatten)]
+    pub styles: BTreeMap<ThemeSlot, StyleSpec>,
+}
+
+// Style written as words, like "bold yellow on black": modifiers, a foreground colour and
+// a background colour after "on".
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StyleSpec(pub Style);
+
+impl FromStr for StyleSpec {
+    type Err = TimerError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut style = Style::default();
+        let mut words = value.split_whitespace();
+        while let Some(word) = words.next() {
+            style = match word.to_lowercase().as_str() {
+                "bold" => style.add_modifier(Modifier::BOLD),
+                "dim" => style.add_modifier(Modifier::DIM),
+                "italic" => style.add_modifier(Modifier::ITALIC),
+                "underlined" => style.add_modifier(Modifier::UNDERLINED),
+                "reversed" => style.add_modifier(Modifier::REVERSED),
+                "on" => style.bg(parse_color(words.next().unwrap_or(""))?),
+                color => style.fg(parse_color(color)?),
+            };
+        }
+        Ok(Self(style))
+    }
+}
+
+impl<'de> Deserialize<'de> for StyleSpec {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+fn parse_color(value: &str) -> Result<Color, TimerError> {
+    value
+        .parse()
+        .map_err(|_| TimerError::new(&format!("Unknown colour: {}.", value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn themes(toml: &str) -> BTreeMap<String, ThemeConfig> {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn custom_theme_changes_slots_of_its_base() {
+        let themes = themes(
+            r##"
+            [dark]
+            clock = "bold #ff8800"
+
+            [night]
+            base = "dark"
+            key = "italic cyan on black"
+            "##,
+        );
+
+        let dark = Theme::resolve("dark", &themes).unwrap();
+        assert_eq!(
+            dark.clock,
+            Style::default()
+                .add_modifier(Modifier::BOLD)
+                .fg(Color::Rgb(0xff, 0x88, 0x00))
+        );
+        assert_eq!(dark.title, Theme::dark().title);
+
+        let night = Theme::resolve("night", &themes).unwrap();
+        assert_eq!(night.clock, dark.clock);
+        assert_eq!(
+            night.key,
+            Style::default()
+                .add_modifier(Modifier::ITALIC)
+                .fg(Color::Cyan)
+                .bg(Color::Black)
+        );
+        assert_eq!(Theme::resolve("light", &themes).unwrap(), Theme::light());
+    }
+
+    #[test]
+    fn unknown_and_circular_themes_are_errors() {
+        let themes = themes(
+            r#"
+            [a]
+            base = "b"
+
+            [b]
+            base = "a"
+
+            [c]
+            base = "sepia"
+            "#,
+        );
+
+        let error = Theme::resolve("a", &themes).unwrap_err();
+        assert_eq!(error.details, "Theme a is based on itself.");
+        let error = Theme::resolve("c", &themes).unwrap_err();
+        assert!(error.details.starts_with("Unknown theme: sepia."));
+    }
+
+    #[test]
+    fn colours_are_parsed() {
+        assert_eq!(parse_color("yellow").unwrap(), Color::Yellow);
+        assert_eq!(parse_color("LightBlue").unwrap(), Color::LightBlue);
+        assert_eq!(
+            parse_color("#1e90ff").unwrap(),
+            Color::Rgb(0x1e, 0x90, 0xff)
+        );
+        assert_eq!(parse_color("42").unwrap(), Color::Indexed(42));
+        for invalid in ["mauve", "#12345", ""] {
+            let error = parse_color(invalid).unwrap_err();
+            assert_eq!(error.details, format!("Unknown colour: {}.", invalid));
+        }
+        assert!("bold on".parse::<StyleSpec>().is_err());
+    }
+
+    #[test]
+    fn no_color_selects_monochrome() {
+        let themes = BTreeMap::new();
+        let select =
+            |no_color: Option<&str>| Theme::select("light", &themes, no_color.map(OsStr::new));
+
+        assert_eq!(select(Some("1")), Theme::monochrome());
+        assert_eq!(select(Some("")), Theme::light());
+        assert_eq!(select(None), Theme::light());
+    }
+}
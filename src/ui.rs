@@ -1,18 +1,24 @@
-use std::time::{SystemTime, UNIX_EPOCH};
-
-use chrono::DateTime;
+use chrono::Weekday;
 use ratatui::{
     layout::{Alignment, Constraint, Layout, Rect},
-    style::{Color, Modifier, Style, Stylize},
+    style::Style,
     text::{self, Line, Span, Text},
-    widgets::{Block, BorderType, Borders, List, ListItem, Paragraph, Wrap},
+    widgets::{Block, BorderType, Borders, Clear, List, ListItem, Paragraph},
     Frame,
 };
 use tui_confirm_dialog::ConfirmDialog;
 
-use crate::app::{
-    App, InputMode, StatefulList, PROJECT_INPUT_PANEL_INDEX, PROJECT_LIST_PANEL_INDEX,
-    TIMER_BUTTONS_PANEL_INDEX, TIMER_LIST_PANEL_INDEX,
+use crate::{
+    action::Action,
+    app::{
+        App, InputMode, PanelAreas, StatefulList, PROJECT_INPUT_PANEL_INDEX, PROJECT_LIST_PANEL_INDEX,
+        TIMER_BUTTONS_PANEL_INDEX, TIMER_LIST_PANEL_INDEX,
+    },
+    datetime::{DurationFormat, Formats, Zone},
+    export::ExportFilter,
+    model::{project::Project, rounding::Rounding},
+    report::Report,
+    theme::Theme,
 };
 
 pub fn render(frame: &mut Frame) {
@@ -20,56 +26,120 @@ pub fn render(frame: &mut Frame) {
     frame.render_widget(text, frame.area());
 }
 
+// Smallest usable terminal; panels are stacked below NARROW_WIDTH columns and lists get
+// extra columns from WIDE_WIDTH on.
+const MIN_WIDTH: u16 = 40;
+const MIN_HEIGHT: u16 = 15;
+const NARROW_WIDTH: u16 = 90;
+const WIDE_WIDTH: u16 = 140;
+const BIG_DIGIT_HEIGHT: u16 = 5;
+
+#[derive(Clone, Copy, PartialEq)]
+enum LayoutMode {
+    Stacked,
+    Split,
+    Wide,
+}
+
+impl LayoutMode {
+    fn new(area: Rect) -> Self {
+        if area.width < NARROW_WIDTH {
+            LayoutMode::Stacked
+        } else if area.width < WIDE_WIDTH {
+            LayoutMode::Split
+        } else {
+            LayoutMode::Wide
+        }
+    }
+}
+
 pub fn draw(frame: &mut Frame, app: &mut App) {
+    let area = frame.area();
+    if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+        draw_too_small(frame, app);
+        return;
+    }
+
+    // Block digits of the running timer need a taller header.
+    let big_timer = app.big_timer
+        && app.running_timer().is_some()
+        && area.height >= MIN_HEIGHT + BIG_DIGIT_HEIGHT;
+    let header_height = if big_timer { BIG_DIGIT_HEIGHT + 3 } else { 3 };
     let chunks = Layout::vertical([
-        Constraint::Length(3),
+        Constraint::Length(header_height),
         Constraint::Fill(1),
-        Constraint::Length(25),
+        Constraint::Length(1),
     ])
-    .split(frame.area());
+    .split(area);
 
+    let mode = LayoutMode::new(area);
     draw_header(frame, app, chunks[0], TIMER_BUTTONS_PANEL_INDEX);
-    draw_content(frame, app, chunks[1]);
-    draw_text(frame, app, chunks[2]);
+    draw_content(frame, app, chunks[1], mode);
+    draw_status(frame, app, chunks[2]);
+
+    if app.show_help {
+        draw_help(frame, app);
+    }
+    if app.palette.is_some() {
+        draw_palette(frame, app);
+    }
 
     if app.confirm_dialog_component.confirm_popup.is_opened() {
 		let popup = ConfirmDialog::default()
 			.borders(Borders::ALL)
-			.bg(Color::Black)
+			.bg(app.theme.popup.bg.unwrap_or_default())
+			.border_style(app.theme.popup)
 			.border_type(BorderType::Rounded)
-			.button_style(Style::default())
-			.selected_button_style(Style::default().yellow().underlined().bold());
+			.button_style(app.theme.popup)
+			.selected_button_style(app.theme.selected_button);
 		frame.render_stateful_widget(popup, frame.area(), &mut app.confirm_dialog_component.confirm_popup);
 	}
 }
 
 fn draw_header(frame: &mut Frame, app: &mut App, area: Rect, panel_index: usize) {
-    let (border_color, border_type) = get_border_styles(app.selected_panel_index == panel_index);
+    let (border_style, border_type) =
+        get_border_styles(app.selected_panel_index == panel_index, &app.theme);
 
     let block = Block::default()
         .borders(Borders::ALL)
         .border_type(border_type)
-        .border_style(Style::default().fg(border_color));
+        .border_style(border_style);
     frame.render_widget(block, area);
 
-    let chunks = Layout::vertical([Constraint::Length(2), Constraint::Min(2)]).split(area);
+    // Buttons are as wide as their labels; the clock goes first when there is no room.
+    let button_widths: Vec<u16> = app
+        .timer_buttons
+        .items
+        .iter()
+        .map(|button| button.text.chars().count() as u16 + 4)
+        .collect();
+    let buttons_width: u16 = button_widths.iter().sum();
+    let time_text = get_current_time(&app.zone, &app.formats, app.clock.now());
+    let time_width = time_text.chars().count() as u16 + 2;
+    let time_width = if area.width >= buttons_width + time_width + 14 {
+        time_width
+    } else {
+        0
+    };
+
+    let row = Rect::new(area.x, area.y + 1, area.width, 1);
     let chunks = Layout::horizontal([
         Constraint::Length(1),
-        Constraint::Fill(2),
         Constraint::Fill(1),
-        Constraint::Length(20),
+        Constraint::Length(buttons_width),
+        Constraint::Length(time_width),
         Constraint::Length(1),
     ])
-    .split(chunks[1]);
+    .split(row);
 
     let timer_chunks = Layout::horizontal(
-        app.timer_buttons
-            .items
+        button_widths
             .iter()
-            .map(|_| Constraint::Length(15))
+            .map(|width| Constraint::Length(*width))
             .collect::<Vec<_>>(),
     )
     .split(chunks[2]);
+    app.areas.buttons = timer_chunks.to_vec();
 
     let selected_button_index = app.timer_buttons.state.selected().unwrap_or(9999);
     app.timer_buttons
@@ -86,96 +156,223 @@ fn draw_header(frame: &mut Frame, app: &mut App, area: Rect, panel_index: usize)
             );
             let paragraph = Paragraph::new(text::Line::from(Span::styled(
                 content,
-                Style::default().fg(Color::Green),
+                app.theme.button,
             )))
             .alignment(Alignment::Right);
             frame.render_widget(paragraph, timer_chunks[i]);
         });
-    let selected_project_name = app
-        .projects
-        .selected()
-        .map(|project| project.name.clone())
-        .unwrap_or("No project selected".to_owned());
-
-    let content_project = Paragraph::new(Text::from(Span::styled(
-        selected_project_name,
-        Style::default()
-            .add_modifier(Modifier::BOLD)
-            .fg(Color::Green),
-    )));
-
-    let time_text = get_elapsed_time_since_midnight();
+    // A running timer is shown whichever project is selected.
+    let running = app.running_timer().map(|(project, timer)| {
+        let now = app.clock.now();
+        let (today, _) = get_project_totals(project, &app.zone, now);
+        (project.name.clone(), now.saturating_sub(timer.start_time), today)
+    });
+    let content_project = match &running {
+        Some((name, elapsed, today)) => {
+            let elapsed = DurationFormat::Hms.format(*elapsed);
+            let today = app.formats.duration.format(*today);
+            // The name gives way to the times when space is short.
+            let times_width = elapsed.chars().count() + today.chars().count() + 12;
+            let name_width = (chunks[1].width as usize).saturating_sub(times_width);
+            Paragraph::new(Line::from(vec![
+                Span::styled(format!("● {}  ", truncate(name, name_width)), app.theme.header),
+                Span::styled(elapsed, app.theme.running_timer),
+                Span::raw("  today "),
+                Span::styled(today, app.theme.duration),
+            ]))
+        }
+        None => {
+            let selected_project_name = app
+                .projects
+                .selected()
+                .map(|project| project.name.clone())
+                .unwrap_or("No project selected".to_owned());
+            Paragraph::new(Text::from(Span::styled(
+                selected_project_name,
+                app.theme.header,
+            )))
+        }
+    };
 
     let content_time = Paragraph::new(Text::from(Span::styled(
         time_text.to_string(),
-        Style::default().add_modifier(Modifier::BOLD).fg(Color::Red),
+        app.theme.clock,
     )))
     .alignment(Alignment::Right);
 
     frame.render_widget(content_project, chunks[1]);
     frame.render_widget(content_time, chunks[3]);
+
+    if let Some((_, elapsed, _)) = running {
+        if area.height >= BIG_DIGIT_HEIGHT + 3 {
+            let lines: Vec<Line> = big_digits(&DurationFormat::Hms.format(elapsed))
+                .into_iter()
+                .map(|line| Line::from(Span::styled(line, app.theme.running_timer)))
+                .collect();
+            let digits_area = Rect::new(
+                area.x + 1,
+                area.y + 2,
+                area.width.saturating_sub(2),
+                BIG_DIGIT_HEIGHT,
+            );
+            frame.render_widget(
+                Paragraph::new(lines).alignment(Alignment::Center),
+                digits_area,
+            );
+        }
+    }
 }
 
-fn draw_content(frame: &mut Frame, app: &mut App, area: Rect) {
+// Text drawn with block characters, five rows high; only digits and colons are needed.
+fn big_digits(text: &str) -> Vec<String> {
+    let mut lines = vec![String::new(); BIG_DIGIT_HEIGHT as usize];
+    for (i, c) in text.chars().enumerate() {
+        let glyph: [&str; 5] = match c {
+            '0' => ["███", "█ █", "█ █", "█ █", "███"],
+            '1' => [" █ ", "██ ", " █ ", " █ ", "███"],
+            '2' => ["███", "  █", "███", "█  ", "███"],
+            '3' => ["███", "  █", "███", "  █", "███"],
+            '4' => ["█ █", "█ █", "███", "  █", "  █"],
+            '5' => ["███", "█  ", "███", "  █", "███"],
+            '6' => ["███", "█  ", "███", "█ █", "███"],
+            '7' => ["███", "  █", "  █", "  █", "  █"],
+            '8' => ["███", "█ █", "███", "█ █", "███"],
+            '9' => ["███", "█ █", "███", "  █", "███"],
+            ':' => [" ", "█", " ", "█", " "],
+            _ => ["   "; 5],
+        };
+        for (line, row) in lines.iter_mut().zip(glyph) {
+            if i > 0 {
+                line.push(' ');
+            }
+            line.push_str(row);
+        }
+    }
+    lines
+}
+
+fn draw_content(frame: &mut Frame, app: &mut App, area: Rect, mode: LayoutMode) {
+    if mode == LayoutMode::Stacked {
+        let chunks = Layout::vertical([
+            Constraint::Fill(2),
+            Constraint::Length(3),
+            Constraint::Fill(3),
+        ])
+        .split(area);
+        draw_project_list(frame, app, chunks[0], PROJECT_LIST_PANEL_INDEX, mode);
+        draw_project_input(frame, app, chunks[1], PROJECT_INPUT_PANEL_INDEX);
+        draw_timer_list(frame, app, chunks[2], TIMER_LIST_PANEL_INDEX, mode);
+        return;
+    }
+
     let constraints = vec![Constraint::Percentage(50), Constraint::Percentage(50)];
     let chunks = Layout::horizontal(constraints).split(area);
-    draw_timer_list(frame, app, chunks[1], TIMER_LIST_PANEL_INDEX);
+    draw_timer_list(frame, app, chunks[1], TIMER_LIST_PANEL_INDEX, mode);
 
     let chunks =
         Layout::vertical(vec![Constraint::Fill(1), Constraint::Length(3)]).split(chunks[0]);
 
-    draw_project_list(frame, app, chunks[0], PROJECT_LIST_PANEL_INDEX);
+    draw_project_list(frame, app, chunks[0], PROJECT_LIST_PANEL_INDEX, mode);
     draw_project_input(frame, app, chunks[1], PROJECT_INPUT_PANEL_INDEX);
 }
 
-fn draw_project_list(frame: &mut Frame, app: &mut App, area: Rect, panel_index: usize) {
-    let (border_color, border_type) = get_border_styles(app.selected_panel_index == panel_index);
-
+fn draw_project_list(
+    frame: &mut Frame,
+    app: &mut App,
+    area: Rect,
+    panel_index: usize,
+    mode: LayoutMode,
+) {
+    let (border_style, border_type) =
+        get_border_styles(app.selected_panel_index == panel_index, &app.theme);
+
+    // Wide screens show today's and this week's total next to each project.
+    let width = area.width.saturating_sub(4) as usize;
+    let now = app.clock.now();
     let projects: Vec<ListItem> = app
         .projects
         .items
         .iter()
-        .map(|project| ListItem::new(vec![text::Line::from(Span::raw(project.name.clone()))]))
+        .map(|project| {
+            if mode != LayoutMode::Wide {
+                return ListItem::new(vec![text::Line::from(Span::raw(project.name.clone()))]);
+            }
+            let (today, week) = get_project_totals(project, &app.zone, now);
+            let totals = format!(
+                "{:>12} {:>12}",
+                app.formats.duration.format(today),
+                app.formats.duration.format(week)
+            );
+            let name_width = width.saturating_sub(totals.chars().count() + 1);
+            ListItem::new(vec![text::Line::from(vec![
+                Span::raw(format!("{:<name_width$.name_width$} ", project.name)),
+                Span::styled(totals, app.theme.duration),
+            ])])
+        })
         .collect();
 
     let projects = List::new(projects)
         .block(
             Block::bordered()
-                .title("Projects")
+                .title(if mode == LayoutMode::Wide {
+                    "Projects (today, this week)"
+                } else {
+                    "Projects"
+                })
                 .border_type(border_type)
-                .border_style(Style::default().fg(border_color)),
+                .border_style(border_style),
         )
-        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .highlight_style(app.theme.highlight)
         .highlight_symbol("> ");
 
     frame.render_stateful_widget(projects, area, &mut app.projects.state);
+    app.areas.project_list = area;
 }
 
-fn draw_timer_list(frame: &mut Frame, app: &mut App, area: Rect, panel_index: usize) {
-    let (border_color, border_type) = get_border_styles(app.selected_panel_index == panel_index);
-
+fn draw_timer_list(
+    frame: &mut Frame,
+    app: &mut App,
+    area: Rect,
+    panel_index: usize,
+    mode: LayoutMode,
+) {
+    let (border_style, border_type) =
+        get_border_styles(app.selected_panel_index == panel_index, &app.theme);
+
+    app.areas.timer_list = area;
+    let now = app.clock.now();
     let selected_project = app.projects.selected();
 
     if let Some(project) = selected_project {
+        let title = get_timer_list_title(project, &app.zone, &app.formats, now);
         let timers = StatefulList::with_items(project.timers.clone());
 
         let timers: Vec<ListItem> = timers
             .items
             .iter()
             .map(|timer| {
-                let start_time = get_formated_date_time(Some(timer.start_time));
-                let end_time = get_formated_date_time(timer.end_time);
-                let duration = get_duration(timer.start_time, timer.end_time);
+                let start_time =
+                    get_formated_date_time(Some(timer.start_time), &app.zone, &app.formats);
+                let end_time = get_formated_date_time(timer.end_time, &app.zone, &app.formats);
+                let duration = get_duration(timer.start_time, timer.end_time, &app.formats, now);
 
                 ListItem::new(vec![
                     text::Line::from(Span::styled(
                         format!("{} - {}", start_time, end_time),
-                        Style::default().fg(Color::Green),
-                    )),
-                    text::Line::from(Span::styled(
-                        format!("Duration: {}", duration),
-                        Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                        if timer.end_time.is_some() {
+                            app.theme.timestamp
+                        } else {
+                            app.theme.running_timer
+                        },
                     )),
+                    text::Line::from(vec![
+                        Span::styled(format!("Duration: {}", duration), app.theme.duration),
+                        // The note fits next to the duration on wide screens.
+                        Span::raw(match (&timer.note, mode) {
+                            (Some(note), LayoutMode::Wide) => format!("  {}", note),
+                            _ => String::new(),
+                        }),
+                    ]),
                     text::Line::from(Span::raw("")),
                 ])
             })
@@ -184,27 +381,29 @@ fn draw_timer_list(frame: &mut Frame, app: &mut App, area: Rect, panel_index: us
         let timers = List::new(timers)
             .block(
                 Block::bordered()
-                    .title("Timers")
+                    .title(title)
                     .border_type(border_type)
-                    .border_style(Style::default().fg(border_color)),
+                    .border_style(border_style),
             )
-            .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+            .highlight_style(app.theme.highlight)
             .highlight_symbol("> ");
 
-        frame.render_widget(timers, area);
+        frame.render_stateful_widget(timers, area, &mut app.timers);
     } else {
         let block = Block::default()
             .title("TIMER LIST")
             .borders(Borders::ALL)
             .border_type(border_type)
-            .border_style(Style::default().fg(border_color));
+            .border_style(border_style);
 
         frame.render_widget(block, area);
     }
 }
 
 fn draw_project_input(frame: &mut Frame, app: &mut App, area: Rect, panel_index: usize) {
-    let (border_color, border_type) = get_border_styles(app.selected_panel_index == panel_index);
+    app.areas.project_input = area;
+    let (border_style, border_type) =
+        get_border_styles(app.selected_panel_index == panel_index, &app.theme);
 
     let width = area.width.max(3) - 3;
     let scroll = app.project_input.input.visual_scroll(width as usize);
@@ -212,7 +411,7 @@ fn draw_project_input(frame: &mut Frame, app: &mut App, area: Rect, panel_index:
     let input = Paragraph::new(app.project_input.input.value())
         .style(match app.project_input.mode {
             InputMode::Normal => Style::default(),
-            InputMode::Editing => Style::default().fg(Color::Yellow),
+            InputMode::Editing => app.theme.editing,
         })
         .scroll((0, scroll as u16))
         .block(
@@ -220,7 +419,7 @@ fn draw_project_input(frame: &mut Frame, app: &mut App, area: Rect, panel_index:
                 .borders(Borders::ALL)
                 .title("Edit project")
                 .border_type(border_type)
-                .border_style(Style::default().fg(border_color)),
+                .border_style(border_style),
         );
     frame.render_widget(input, area);
 
@@ -234,86 +433,455 @@ fn draw_project_input(frame: &mut Frame, app: &mut App, area: Rect, panel_index:
     }
 }
 
-const HELP_TEXT_TIMER_PANEL: [&str; 5] = [
-    "Start timer",
-    "To start timer select Start button with ← and → or with 'a' and 'd' keys and press ⏎ key to start timer.",
-    "Stop timer",
-    "To stop timer select Stop button with ← and → or with 'a' and 'd' keys and press ⏎ key to stop timer.",
-    "TODO"
+const HELP_TIMER_BUTTONS_PANEL: [Action; 10] = [
+    Action::Left,
+    Action::Right,
+    Action::Confirm,
+    Action::NewProject,
+    Action::StartTimer,
+    Action::StopTimer,
+    Action::NextPanel,
+    Action::CommandPalette,
+    Action::Help,
+    Action::Quit,
+];
+
+const HELP_PROJECT_PANEL: [Action; 14] = [
+    Action::Up,
+    Action::Down,
+    Action::First,
+    Action::Last,
+    Action::NewProject,
+    Action::EditProject,
+    Action::DeleteProject,
+    Action::StartTimer,
+    Action::StopTimer,
+    Action::ExportCsv,
+    Action::NextPanel,
+    Action::CommandPalette,
+    Action::Help,
+    Action::Quit,
 ];
 
-const HELP_PROJECT_PANEL: [&str; 5] = [
-    "Select project",
-    "To select project use ↑ and ↓ keys or use 'a' and 'd' keys.",
-    "Edit project",
-    "Select project with above manual, and pres 'e' key to start editing project.",
-    "TODO",
+const HELP_PROJECT_INPUT_PANEL: [Action; 3] = [
+    Action::Confirm,
+    Action::Cancel,
+    Action::Quit,
 ];
 
-fn draw_text(frame: &mut Frame, app: &mut App, area: Rect) {
-    let text: Vec<Line<'_>> = match app.selected_panel_index {
-        TIMER_BUTTONS_PANEL_INDEX => HELP_TEXT_TIMER_PANEL,
-        _ => HELP_PROJECT_PANEL,
+const HELP_TIMER_LIST_PANEL: [Action; 10] = [
+    Action::Up,
+    Action::Down,
+    Action::First,
+    Action::Last,
+    Action::StartTimer,
+    Action::StopTimer,
+    Action::NextPanel,
+    Action::CommandPalette,
+    Action::Help,
+    Action::Quit,
+];
+
+fn draw_status(frame: &mut Frame, app: &mut App, area: Rect) {
+    let help_keys = app.keymap.describe(Action::Help);
+    let hint_width = if help_keys.is_empty() {
+        0
+    } else {
+        help_keys.chars().count() as u16 + 6
+    };
+    let chunks =
+        Layout::horizontal([Constraint::Fill(1), Constraint::Length(hint_width)]).split(area);
+
+    let status = if let Some(error) = &app.error {
+        Span::styled(error.clone(), app.theme.error)
+    } else if let Some(message) = &app.message {
+        Span::styled(message.clone(), app.theme.message)
+    } else {
+        Span::raw("")
+    };
+    frame.render_widget(Paragraph::new(Line::from(status)), chunks[0]);
+
+    if !help_keys.is_empty() {
+        let hint = Line::from(vec![
+            Span::styled(help_keys, app.theme.key),
+            Span::raw(" Help"),
+        ]);
+        frame.render_widget(Paragraph::new(hint).alignment(Alignment::Right), chunks[1]);
+    }
+}
+
+// Lists bindings of the focused panel (or of editing), taken from the active keymap so
+// rebound keys are shown and unbound actions are left out.
+fn draw_help(frame: &mut Frame, app: &mut App) {
+    let help = if app.project_input.mode == InputMode::Editing {
+        HELP_PROJECT_INPUT_PANEL.as_slice()
+    } else {
+        match app.selected_panel_index {
+            TIMER_BUTTONS_PANEL_INDEX => HELP_TIMER_BUTTONS_PANEL.as_slice(),
+            PROJECT_INPUT_PANEL_INDEX => HELP_PROJECT_INPUT_PANEL.as_slice(),
+            TIMER_LIST_PANEL_INDEX => HELP_TIMER_LIST_PANEL.as_slice(),
+            _ => HELP_PROJECT_PANEL.as_slice(),
+        }
+    };
+    let bindings: Vec<(String, &str)> = help
+        .iter()
+        .map(|action| (app.keymap.describe(*action), action.description()))
+        .filter(|(keys, _)| !keys.is_empty())
+        .collect();
+
+    let keys_width = bindings
+        .iter()
+        .map(|(keys, _)| keys.chars().count())
+        .max()
+        .unwrap_or(0);
+    let description_width = bindings
+        .iter()
+        .map(|(_, description)| description.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    // When the list is taller than the terminal it is folded into columns.
+    let rows = (frame.area().height.saturating_sub(2) as usize).max(1);
+    let columns = bindings.len().div_ceil(rows).max(1);
+    let rows = bindings.len().div_ceil(columns);
+    let count = bindings.len();
+    let mut text: Vec<Line<'_>> = vec![Line::default(); rows];
+    for (i, (keys, description)) in bindings.into_iter().enumerate() {
+        let line = &mut text[i % rows];
+        if i >= rows {
+            line.push_span(Span::raw("   "));
+        }
+        line.push_span(Span::styled(
+            format!("{:<width$}  ", keys, width = keys_width),
+            app.theme.key,
+        ));
+        let width = if i + rows < count { description_width } else { 0 };
+        line.push_span(Span::raw(format!("{:<width$}", description)));
     }
-    .iter()
-    .map(|x| text::Line::from(x.to_string()))
-    .collect();
-
-    let block = Block::bordered().title(Span::styled(
-        "Manual",
-        Style::default()
-            .fg(Color::Magenta)
-            .add_modifier(Modifier::BOLD),
+
+    let width = text.iter().map(Line::width).max().unwrap_or(0) as u16 + 4;
+    let area = centered_rect(frame.area(), width, text.len() as u16 + 2);
+    let block = Block::bordered()
+        .border_type(BorderType::Rounded)
+        .border_style(app.theme.focused_border)
+        .title(Span::styled(" Help ", app.theme.title))
+        .style(app.theme.popup);
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(Paragraph::new(text).block(block), area);
+}
+
+fn draw_palette(frame: &mut Frame, app: &mut App) {
+    let Some(palette) = app.palette.as_mut() else {
+        return;
+    };
+    let frame_area = frame.area();
+    let height = (palette.entries.len() as u16 + 3).min(frame_area.height * 3 / 5).max(4);
+    let mut area = centered_rect(frame_area, 60, height);
+    area.y = frame_area.y + frame_area.height / 5;
+    area.height = area.height.min(frame_area.bottom() - area.y);
+
+    let block = Block::bordered()
+        .border_type(BorderType::Rounded)
+        .border_style(app.theme.focused_border)
+        .title(Span::styled(" Commands ", app.theme.title))
+        .style(app.theme.popup);
+    let inner = block.inner(area);
+    frame.render_widget(Clear, area);
+    frame.render_widget(block, area);
+
+    let chunks = Layout::vertical([Constraint::Length(1), Constraint::Fill(1)]).split(inner);
+    let width = chunks[0].width.max(3) - 3;
+    let scroll = palette.input.visual_scroll(width as usize);
+    let input = Paragraph::new(Line::from(vec![
+        Span::styled("> ", app.theme.key),
+        Span::styled(palette.input.value(), app.theme.editing),
+    ]))
+    .scroll((0, scroll as u16));
+    frame.render_widget(input, chunks[0]);
+    frame.set_cursor_position((
+        chunks[0].x + (palette.input.visual_cursor().max(scroll) - scroll) as u16 + 2,
+        chunks[0].y,
     ));
-    let paragraph = Paragraph::new(text).block(block).wrap(Wrap { trim: true });
-    frame.render_widget(paragraph, area);
+
+    // Labels on the left, bindings aligned to the right edge.
+    let width = chunks[1].width.saturating_sub(2) as usize;
+    let items: Vec<ListItem> = palette
+        .matches
+        .iter()
+        .map(|i| {
+            let entry = &palette.entries[*i];
+            let keys_width = entry.keys.chars().count();
+            let label_width = width.saturating_sub(keys_width + 1);
+            ListItem::new(Line::from(vec![
+                Span::raw(format!("{:<label_width$} ", entry.label)),
+                Span::styled(entry.keys.clone(), app.theme.key),
+            ]))
+        })
+        .collect();
+    let list = List::new(items)
+        .highlight_style(app.theme.highlight)
+        .highlight_symbol("> ");
+    frame.render_stateful_widget(list, chunks[1], &mut palette.state);
 }
 
-fn get_elapsed_time_since_midnight() -> String {
-    let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-    let midnight = now - (now % 86400);
-    let elapsed = now - midnight;
-    let hours = elapsed / 3600;
-    let minutes = (elapsed % 3600) / 60;
-    let seconds = elapsed % 60;
-    format!("{:02}:{:02}:{:02}", hours, minutes, seconds)
+fn draw_too_small(frame: &mut Frame, app: &mut App) {
+    // Nothing is drawn, so clicks must not hit panels from the last frame.
+    app.areas = PanelAreas::default();
+    let area = frame.area();
+    let text = vec![
+        Line::from(Span::styled("Terminal too small", app.theme.error)),
+        Line::from(format!("{}x{}", area.width, area.height)),
+        Line::from(format!("Needs at least {}x{}", MIN_WIDTH, MIN_HEIGHT)),
+    ];
+    let area = Rect::new(
+        area.x,
+        area.y + area.height.saturating_sub(3) / 2,
+        area.width,
+        area.height.min(3),
+    );
+    frame.render_widget(Paragraph::new(text).alignment(Alignment::Center), area);
 }
-fn get_formated_date_time(timestamp: Option<u64>) -> String {
-    if let Some(timestamp) = timestamp {
-        let naive_datetime =
-            DateTime::from_timestamp(timestamp as i64, 0).expect("Invalid timestamp");
 
-        naive_datetime.format("%Y-%m-%d %H:%M:%S").to_string()
-    } else {
-        String::new()
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
     }
+    let mut text: String = text.chars().take(width.saturating_sub(1)).collect();
+    text.push('…');
+    text
 }
 
-fn get_duration(start: u64, end: Option<u64>) -> String {
-    let end_time = end.unwrap_or_else(|| {
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("Time went backwards")
-            .as_secs()
-    });
+fn centered_rect(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+fn get_current_time(zone: &Zone, formats: &Formats, now: u64) -> String {
+    zone.format(now, formats.clock.time_format(true))
+        .unwrap_or_default()
+}
+
+fn get_timer_list_title(project: &Project, zone: &Zone, formats: &Formats, now: u64) -> String {
+    let (today, week) = get_project_totals(project, zone, now);
+    format!(
+        "Timers (today {}, this week {})",
+        formats.duration.format(today),
+        formats.duration.format(week)
+    )
+}
+
+// Time tracked on the project today and this week; none when a timer has an invalid timestamp.
+fn get_project_totals(project: &Project, zone: &Zone, now: u64) -> (u64, u64) {
+    let Ok(today) = zone.date(now) else {
+        return (0, 0);
+    };
+    let filter = ExportFilter {
+        from: Some(today.week(Weekday::Mon).first_day()),
+        project: Some(project.id.to_string()),
+        ..ExportFilter::default()
+    };
+    Report::build(
+        std::slice::from_ref(project),
+        &filter,
+        zone,
+        &Rounding::default(),
+        now,
+    )
+    .map_or((0, 0), |report| (report.total_since(today), report.total()))
+}
 
-    let duration_secs = end_time - start;
-    let hours = duration_secs / 3600;
-    let minutes = (duration_secs % 3600) / 60;
-    let seconds = duration_secs % 60;
+fn get_formated_date_time(timestamp: Option<u64>, zone: &Zone, formats: &Formats) -> String {
+    timestamp
+        .map(|timestamp| {
+            zone.format(timestamp, &formats.date_time)
+                .unwrap_or_else(|err| err.details)
+        })
+        .unwrap_or_default()
+}
 
-    format!("{:02}:{:02}:{:02}", hours, minutes, seconds)
+fn get_duration(start: u64, end: Option<u64>, formats: &Formats, now: u64) -> String {
+    let end_time = end.unwrap_or(now);
+
+    formats.duration.format(end_time.saturating_sub(start))
 }
 
-fn get_border_styles(selected: bool) -> (Color, BorderType) {
-    let mut border_color = Color::default();
+fn get_border_styles(selected: bool, theme: &Theme) -> (Style, BorderType) {
+    let mut border_style = theme.border;
     let mut border_type = BorderType::default();
     if selected {
-        border_color = Color::Magenta;
+        border_style = theme.focused_border;
         border_type = BorderType::Thick;
     }
-    (border_color, border_type)
+    (border_style, border_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use ratatui::{backend::TestBackend, Terminal};
+
+    use super::*;
+    use crate::{
+        clock::FixedClock,
+        config::Config,
+        repository::Repository,
+        test_support::{DataFile, TimerSpan, NOW},
+    };
+
+    const HOUR: u64 = 3600;
+
+    fn app_with(file: &DataFile, config: Config) -> App {
+        let config = Config {
+            data_file: file.path(),
+            timezone: Zone::Utc,
+            ..config
+        };
+        let clock = Rc::new(FixedClock::new(NOW));
+        let repository = Repository::open(&config.data_file, config.storage, clock).unwrap();
+        App::new(&config, repository)
+    }
+
+    fn app(file: &DataFile) -> App {
+        app_with(file, Config::default())
+    }
+
+    fn render(app: &mut App, width: u16, height: u16) -> TestBackend {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|frame| draw(frame, app)).unwrap();
+        terminal.backend().clone()
+    }
+
+    fn running_fixture(name: &str) -> DataFile {
+        DataFile::with_projects(
+            name,
+            &[
+                (
+                    "Alpha",
+                    &[
+                        (NOW - 4 * HOUR, Some(NOW - 5 * HOUR / 2)),
+                        (NOW - HOUR - 754, None),
+                    ],
+                ),
+                ("Beta", &[(NOW - 3 * 24 * HOUR, Some(NOW - 3 * 24 * HOUR + HOUR))]),
+            ],
+        )
+    }
+
+    #[test]
+    fn empty() {
+        let file = DataFile::new("ui_empty");
+        let mut app = app(&file);
+        insta::assert_snapshot!(render(&mut app, 100, 20));
+    }
+
+    #[test]
+    fn running_timer_while_other_project_selected() {
+        let file = running_fixture("ui_running");
+        let mut app = app(&file);
+        app.update(Action::NextPanel);
+        app.update(Action::Last);
+        insta::assert_snapshot!(render(&mut app, 100, 20));
+    }
+
+    #[test]
+    fn running_timer_list() {
+        let file = running_fixture("ui_running_list");
+        let mut app = app(&file);
+        app.update(Action::NextPanel);
+        app.update(Action::Down);
+        app.update(Action::NextPanel);
+        app.update(Action::Last);
+        insta::assert_snapshot!(render(&mut app, 100, 20));
+    }
+
+    #[test]
+    fn big_timer() {
+        let file = running_fixture("ui_big_timer");
+        let config = Config {
+            big_timer: true,
+            ..Config::default()
+        };
+        let mut app = app_with(&file, config);
+        insta::assert_snapshot!(render(&mut app, 100, 24));
+    }
+
+    #[test]
+    fn many_projects_scrolled() {
+        let names: Vec<String> = (1..=40).map(|i| format!("Project {:02}", i)).collect();
+        let projects: Vec<(&str, &[TimerSpan])> =
+            names.iter().map(|name| (name.as_str(), &[][..])).collect();
+        let file = DataFile::with_projects("ui_many_projects", &projects);
+        let mut app = app(&file);
+        app.update(Action::NextPanel);
+        app.update(Action::Last);
+        insta::assert_snapshot!(render(&mut app, 100, 20));
+    }
+
+    #[test]
+    fn long_names_stacked() {
+        let file = DataFile::with_projects(
+            "ui_long_names_stacked",
+            &[
+                (
+                    "A project with a name much longer than any panel could ever show in full",
+                    &[(NOW - HOUR, None)],
+                ),
+                ("Short", &[]),
+            ],
+        );
+        let mut app = app(&file);
+        app.update(Action::NextPanel);
+        app.update(Action::Down);
+        insta::assert_snapshot!(render(&mut app, 80, 24));
+    }
+
+    #[test]
+    fn long_names_wide() {
+        let file = DataFile::with_projects(
+            "ui_long_names_wide",
+            &[
+                (
+                    "A project with a name much longer than any panel could ever show in full",
+                    &[(NOW - HOUR, None)],
+                ),
+                ("Short", &[(NOW - 2 * HOUR, Some(NOW - HOUR))]),
+            ],
+        );
+        let mut app = app(&file);
+        app.update(Action::NextPanel);
+        app.update(Action::Down);
+        insta::assert_snapshot!(render(&mut app, 160, 20));
+    }
+
+    #[test]
+    fn terminal_too_small() {
+        let file = DataFile::new("ui_too_small");
+        let mut app = app(&file);
+        insta::assert_snapshot!(render(&mut app, 30, 10));
+    }
+
+    #[test]
+    fn help_overlay() {
+        let file = running_fixture("ui_help");
+        let mut app = app(&file);
+        app.update(Action::NextPanel);
+        app.update(Action::Help);
+        insta::assert_snapshot!(render(&mut app, 100, 24));
+    }
+
+    #[test]
+    fn command_palette() {
+        let file = running_fixture("ui_palette");
+        let mut app = app(&file);
+        app.update(Action::CommandPalette);
+        insta::assert_snapshot!(render(&mut app, 100, 24));
+    }
 }